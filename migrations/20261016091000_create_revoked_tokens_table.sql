@@ -0,0 +1,16 @@
+-- Revoked access tokens (by JWT ID) and per-user revocation cutoff
+CREATE TABLE revoked_tokens (
+    jti UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Indexes
+CREATE INDEX idx_revoked_tokens_user ON revoked_tokens(user_id);
+CREATE INDEX idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tokens_revoked_before TIMESTAMPTZ;
+
+COMMENT ON TABLE revoked_tokens IS 'Access tokens invalidated before their expiry (logout)';
+COMMENT ON COLUMN users.tokens_revoked_before IS 'Access tokens issued before this instant are rejected (logout-all)';
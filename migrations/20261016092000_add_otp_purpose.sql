@@ -0,0 +1,6 @@
+-- Distinguish email verification codes from password reset codes
+ALTER TABLE otp_codes ADD COLUMN IF NOT EXISTS purpose VARCHAR(20) NOT NULL DEFAULT 'verification';
+
+CREATE INDEX IF NOT EXISTS idx_otp_user_purpose ON otp_codes(user_id, purpose);
+
+COMMENT ON COLUMN otp_codes.purpose IS 'verification or password_reset';
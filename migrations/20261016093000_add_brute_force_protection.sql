@@ -0,0 +1,17 @@
+-- Count wrong guesses per OTP code
+ALTER TABLE otp_codes ADD COLUMN IF NOT EXISTS failed_attempts INTEGER NOT NULL DEFAULT 0;
+
+COMMENT ON COLUMN otp_codes.failed_attempts IS 'Wrong guesses; the code is locked after OTP_MAX_ATTEMPTS';
+
+-- Failed login counters per account and per client IP
+CREATE TABLE auth_throttles (
+    key VARCHAR(255) PRIMARY KEY,
+    failures INTEGER NOT NULL DEFAULT 0,
+    locked_until TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_auth_throttles_updated_at ON auth_throttles(updated_at);
+
+COMMENT ON TABLE auth_throttles IS 'Failed authentication attempts, keyed by e.g. login:user:<name> or login:ip:<addr>';
+COMMENT ON COLUMN auth_throttles.locked_until IS 'Attempts are refused until this instant (exponential backoff)';
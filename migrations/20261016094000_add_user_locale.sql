@@ -0,0 +1,4 @@
+-- Preferred language for transactional email
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locale VARCHAR(35);
+
+COMMENT ON COLUMN users.locale IS 'Preferred locale, e.g. en or es-MX (NULL uses EMAIL_DEFAULT_LOCALE)';
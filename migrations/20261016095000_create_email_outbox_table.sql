@@ -0,0 +1,25 @@
+-- Outgoing email, written in the same transaction as the change that triggers it
+CREATE TABLE IF NOT EXISTS email_outbox (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    template VARCHAR(50) NOT NULL,
+    recipient VARCHAR(255) NOT NULL,
+    subject TEXT NOT NULL,
+    html TEXT NOT NULL,
+    text TEXT,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Indexes
+CREATE INDEX IF NOT EXISTS idx_email_outbox_due ON email_outbox(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_email_outbox_user_id ON email_outbox(user_id);
+
+COMMENT ON TABLE email_outbox IS 'Rendered transactional email awaiting delivery by the background worker';
+COMMENT ON COLUMN email_outbox.status IS 'pending, sent or dead (gave up after EMAIL_MAX_ATTEMPTS)';
+COMMENT ON COLUMN email_outbox.next_attempt_at IS 'Earliest next delivery attempt; also acts as the lease of a claimed message';
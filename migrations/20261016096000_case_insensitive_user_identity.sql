@@ -0,0 +1,29 @@
+-- Make usernames and emails unique regardless of case; emails are stored lowercased
+
+-- Refuse to migrate while case-insensitive duplicates exist; they must be merged or renamed by hand
+DO $$
+DECLARE
+    duplicate_emails TEXT;
+    duplicate_usernames TEXT;
+BEGIN
+    SELECT string_agg(email, ', ') INTO duplicate_emails
+    FROM (SELECT LOWER(TRIM(email)) AS email FROM users GROUP BY 1 HAVING COUNT(*) > 1) d;
+
+    SELECT string_agg(username, ', ') INTO duplicate_usernames
+    FROM (SELECT LOWER(TRIM(username)) AS username FROM users GROUP BY 1 HAVING COUNT(*) > 1) d;
+
+    IF duplicate_emails IS NOT NULL OR duplicate_usernames IS NOT NULL THEN
+        RAISE EXCEPTION 'Case-insensitive duplicate users must be resolved first. Emails: %. Usernames: %',
+            COALESCE(duplicate_emails, 'none'), COALESCE(duplicate_usernames, 'none');
+    END IF;
+END $$;
+
+UPDATE users SET email = LOWER(TRIM(email)) WHERE email <> LOWER(TRIM(email));
+UPDATE users SET username = TRIM(username) WHERE username <> TRIM(username);
+
+-- Indexes
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_username_lower ON users(LOWER(username));
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_email_lower ON users(LOWER(email));
+
+COMMENT ON COLUMN users.email IS 'Email address, stored lowercased';
+COMMENT ON COLUMN users.username IS 'Username as chosen by the user; unique case-insensitively';
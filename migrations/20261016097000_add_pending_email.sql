@@ -0,0 +1,5 @@
+-- New email address awaiting confirmation with an email_change code
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_email VARCHAR(255);
+
+COMMENT ON COLUMN users.pending_email IS 'Requested new email (lowercased); replaces email once the code sent to it is verified';
+COMMENT ON COLUMN otp_codes.purpose IS 'verification, password_reset or email_change';
@@ -0,0 +1,35 @@
+-- TOTP two-factor authentication
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret VARCHAR(64);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_enabled_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_step BIGINT;
+
+-- One-time recovery codes (only the SHA-256 hash is stored)
+CREATE TABLE totp_recovery_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Second login step after a correct password (only the SHA-256 hash is stored)
+CREATE TABLE login_challenges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ NOT NULL,
+    consumed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Indexes
+CREATE UNIQUE INDEX idx_totp_recovery_codes_user_code ON totp_recovery_codes(user_id, code_hash);
+CREATE INDEX idx_login_challenges_user ON login_challenges(user_id);
+CREATE INDEX idx_login_challenges_expires_at ON login_challenges(expires_at);
+
+COMMENT ON COLUMN users.totp_secret IS 'Base32 TOTP secret; set at enrollment, active once totp_enabled_at is set';
+COMMENT ON COLUMN users.totp_enabled_at IS 'When 2FA was confirmed with a first code; NULL while disabled or pending';
+COMMENT ON COLUMN users.totp_last_step IS 'Last accepted TOTP time step, so a code cannot be replayed';
+COMMENT ON TABLE totp_recovery_codes IS 'Single-use codes that replace a TOTP code when the authenticator is lost';
+COMMENT ON TABLE login_challenges IS 'Short-lived tokens issued by login when 2FA is enabled';
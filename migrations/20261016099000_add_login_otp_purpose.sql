@@ -0,0 +1,2 @@
+-- Passwordless sign-in reuses otp_codes with its own purpose
+COMMENT ON COLUMN otp_codes.purpose IS 'verification, password_reset, email_change or login';
@@ -0,0 +1,31 @@
+-- Accounts at external OpenID Connect providers linked to users
+CREATE TABLE oidc_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(provider, subject)
+);
+
+-- Sign-ins started at a provider and not yet completed (only the SHA-256 hash of the state is stored)
+CREATE TABLE oidc_login_states (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    provider VARCHAR(50) NOT NULL,
+    state_hash VARCHAR(64) UNIQUE NOT NULL,
+    nonce VARCHAR(64) NOT NULL,
+    code_verifier VARCHAR(128) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Indexes
+CREATE INDEX idx_oidc_identities_user ON oidc_identities(user_id);
+CREATE INDEX idx_oidc_login_states_expires_at ON oidc_login_states(expires_at);
+
+COMMENT ON TABLE oidc_identities IS 'External identities (provider + subject) that sign in as a user';
+COMMENT ON COLUMN oidc_identities.provider IS 'Provider name from OIDC_PROVIDERS, e.g. google';
+COMMENT ON COLUMN oidc_identities.subject IS 'The provider''s stable user id (sub claim of the ID token)';
+COMMENT ON TABLE oidc_login_states IS 'PKCE verifier and nonce of a pending authorization code flow; single use';
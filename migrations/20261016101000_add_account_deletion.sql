@@ -0,0 +1,27 @@
+-- Account deletion requested by the user; purged once the grace period is over
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deletion_scheduled_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_users_deletion_scheduled_at ON users(deletion_scheduled_at)
+    WHERE deletion_scheduled_at IS NOT NULL;
+
+-- Joints outlive a deleted creator, so other members keep their messages
+ALTER TABLE joints ALTER COLUMN creator_id DROP NOT NULL;
+ALTER TABLE joints DROP CONSTRAINT IF EXISTS joints_creator_id_fkey;
+ALTER TABLE joints ADD CONSTRAINT joints_creator_id_fkey
+    FOREIGN KEY (creator_id) REFERENCES users(id) ON DELETE SET NULL;
+
+-- Security-relevant account events; rows survive the deletion of the user
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    event VARCHAR(50) NOT NULL,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_events_user ON audit_events(user_id, created_at DESC);
+
+COMMENT ON COLUMN users.deletion_scheduled_at IS 'When the account will be deleted; signing in before then cancels the deletion';
+COMMENT ON COLUMN joints.creator_id IS 'Creator of the joint; NULL once the creator deleted their account';
+COMMENT ON TABLE audit_events IS 'Append-only trail of account events such as deletion_requested or account_deleted';
+COMMENT ON COLUMN audit_events.user_id IS 'Not a foreign key: the user row may no longer exist';
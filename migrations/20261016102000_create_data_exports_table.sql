@@ -0,0 +1,23 @@
+-- Personal data exports requested by users, built by a background job
+CREATE TABLE data_exports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    archive BYTEA,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ
+);
+
+-- Indexes
+CREATE INDEX idx_data_exports_user ON data_exports(user_id, created_at DESC);
+CREATE INDEX idx_data_exports_pending ON data_exports(created_at) WHERE status IN ('pending', 'processing');
+CREATE INDEX idx_data_exports_expires_at ON data_exports(expires_at) WHERE expires_at IS NOT NULL;
+
+COMMENT ON TABLE data_exports IS 'Zip archives of a user''s personal data; removed once expired';
+COMMENT ON COLUMN data_exports.status IS 'pending, processing, ready or failed';
+COMMENT ON COLUMN data_exports.token_hash IS 'SHA-256 hash of the download token';
+COMMENT ON COLUMN data_exports.expires_at IS 'End of the download window, set when the archive is ready';
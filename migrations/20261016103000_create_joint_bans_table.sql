@@ -0,0 +1,17 @@
+-- Users banned from a joint by its creator or a moderator
+CREATE TABLE joint_bans (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    joint_id UUID NOT NULL REFERENCES joints(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    banned_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(joint_id, user_id)
+);
+
+-- Indexes
+CREATE INDEX idx_joint_bans_user ON joint_bans(user_id);
+
+COMMENT ON TABLE joint_bans IS 'Bans keep a user from joining or connecting to a joint';
+COMMENT ON COLUMN joint_bans.expires_at IS 'End of the ban; NULL bans permanently';
@@ -1,17 +1,41 @@
-use axum::{Json, extract::State, http::StatusCode};
-use rand::Rng;
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    api::two_factor::two_factor_challenge,
     errors::AppError,
+    middleware::client_ip::ClientIp,
     models::{
         app_state::AppState,
+        two_factor::TwoFactorChallengeResponse,
         user::{
-            LoginRequest, LoginResponse, RegisterRequest, RegisterResponse, VerifyOtpRequest,
-            VerifyOtpResponse,
+            ChangePasswordRequest, ForgotPasswordRequest, ForgotPasswordResponse, LoginCodeRequest,
+            LoginCodeResponse, LoginRequest, LoginResponse, LogoutRequest, LogoutResponse,
+            RefreshTokenRequest, RegisterRequest, RegisterResponse, ResendOtpRequest,
+            ResendOtpResponse, ResetPasswordRequest, ResetPasswordResponse, VerifyLoginCodeRequest,
+            VerifyOtpRequest, VerifyOtpResponse,
+        },
+    },
+    oidc::pkce::random_token,
+    utils::{
+        account_deletion::cancel_account_deletion,
+        email::{queue_login_code_email, queue_otp_email, queue_password_reset_email},
+        jwt::{Claims, generate_token},
+        normalize::{is_email_identifier, normalize_email, normalize_username},
+        otp::{OtpPurpose, check_otp_send_limit, consume_otp, create_otp, invalidate_otps},
+        password::{hash_password, needs_rehash, verify_password},
+        refresh_token::{
+            create_refresh_token, hash_token, revoke_all_refresh_tokens, revoke_refresh_family,
+            rotate_refresh_token,
         },
+        throttle::{LockoutPolicy, check_throttle, clear_throttle, record_failure},
     },
-    utils::password::hash_password,
 };
 
 /// Register a new user
@@ -28,8 +52,12 @@ use crate::{
 )]
 pub async fn register(
     State(state): State<AppState>,
-    Json(payload): Json<RegisterRequest>,
+    Json(mut payload): Json<RegisterRequest>,
 ) -> Result<(StatusCode, Json<RegisterResponse>), AppError> {
+    // Normalize input
+    payload.email = normalize_email(&payload.email);
+    payload.username = normalize_username(&payload.username);
+
     // Validate input
     payload
         .validate()
@@ -42,9 +70,15 @@ pub async fn register(
         ));
     }
 
-    // Check if username already exists
+    // Check password strength
+    state
+        .config
+        .password_policy
+        .check(&payload.password, &[&payload.username, &payload.email])?;
+
+    // Check if username or email already exists (usernames are case-insensitive)
     let existing_user = sqlx::query!(
-        "SELECT id FROM users WHERE username = $1 OR email = $2",
+        "SELECT id FROM users WHERE LOWER(username) = LOWER($1) OR email = $2",
         payload.username,
         payload.email
     )
@@ -56,46 +90,51 @@ pub async fn register(
     }
 
     // Hash password
-    let password_hash = hash_password(&payload.password)?;
+    let password_hash = hash_password(&payload.password, &state.config.argon2_params).await?;
 
-    // Insert user
+    // Create the user, its OTP code and the verification email atomically
+    let mut tx = state.db.begin().await?;
     let user = sqlx::query!(
         r#"
-        INSERT INTO users (username, email, password_hash, is_18_plus)
-        VALUES ($1, $2, $3, $4)
+        INSERT INTO users (username, email, password_hash, is_18_plus, locale)
+        VALUES ($1, $2, $3, $4, $5)
         RETURNING id, username, email
         "#,
         payload.username,
         payload.email,
         password_hash,
-        payload.is_18_plus
+        payload.is_18_plus,
+        payload.locale
     )
-    .fetch_one(&state.db)
-    .await?;
-
-    // Generate OTP code (6 digits)
-    let otp_code = rand::rng().random_range(100000..999999).to_string();
-
-    // Calculate OTP expiry (10 minutes from now)
-    let expires_at = chrono::Utc::now() + chrono::Duration::minutes(10);
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| match e {
+        // Lost a race against a concurrent registration
+        sqlx::Error::Database(db) if db.is_unique_violation() => AppError::UserAlreadyExists,
+        e => AppError::DatabaseError(e),
+    })?;
 
-    // Save OTP to database
-    sqlx::query!(
-        r#"
-        INSERT INTO otp_codes (user_id, code, expires_at)
-        VALUES ($1, $2, $3)
-        "#,
+    // Generate and save OTP code
+    let otp_code = create_otp(
+        &mut tx,
         user.id,
-        otp_code,
-        expires_at
+        OtpPurpose::Verification,
+        state.config.otp_ttl_minutes,
     )
-    .execute(&state.db)
     .await?;
 
-    // Send OTP via email
-    crate::utils::email::send_otp_email(&payload.email, &otp_code, &state.resend_api_key).await?;
+    // Queue OTP email; the outbox worker delivers it
+    let email_id = queue_otp_email(
+        &mut tx,
+        user.id,
+        &payload.email,
+        state.config.email_locale(payload.locale.as_deref()),
+        &otp_code,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
 
-    tracing::info!("OTP sent to {}", payload.email);
+    tx.commit().await?;
 
     Ok((
         StatusCode::CREATED,
@@ -103,6 +142,7 @@ pub async fn register(
             user_id: user.id,
             username: user.username,
             email: user.email,
+            email_id,
             message: "Registration successful! Please check your email for OTP verification code."
                 .to_string(),
         }),
@@ -118,14 +158,18 @@ pub async fn register(
         (status = 200, description = "Email verified successfully", body = VerifyOtpResponse),
         (status = 400, description = "Invalid or expired OTP"),
         (status = 404, description = "User not found"),
+        (status = 429, description = "Too many wrong guesses, request a new code"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Authentication"
 )]
 pub async fn verify_otp(
     State(state): State<AppState>,
-    Json(payload): Json<VerifyOtpRequest>,
+    Json(mut payload): Json<VerifyOtpRequest>,
 ) -> Result<Json<VerifyOtpResponse>, AppError> {
+    // Normalize input
+    payload.email = normalize_email(&payload.email);
+
     // Validate input
     payload
         .validate()
@@ -137,31 +181,15 @@ pub async fn verify_otp(
         .await?
         .ok_or(AppError::UserNotFound)?;
 
-    // Find valid OTP
-    let otp = sqlx::query!(
-        r#"
-        SELECT id, code, expires_at, is_used
-        FROM otp_codes
-        WHERE user_id = $1 AND code = $2 AND is_used = false
-        ORDER BY created_at DESC
-        LIMIT 1
-        "#,
+    // Check and consume the OTP code
+    consume_otp(
+        &state.db,
         user.id,
-        payload.code
+        &payload.code,
+        OtpPurpose::Verification,
+        state.config.otp_max_attempts,
     )
-    .fetch_optional(&state.db)
-    .await?
-    .ok_or(AppError::InvalidOtp)?;
-
-    // Check if OTP expired
-    if otp.expires_at < chrono::Utc::now() {
-        return Err(AppError::OtpExpired);
-    }
-
-    // Mark OTP as used
-    sqlx::query!("UPDATE otp_codes SET is_used = true WHERE id = $1", otp.id)
-        .execute(&state.db)
-        .await?;
+    .await?;
 
     // Mark user as verified
     sqlx::query!("UPDATE users SET is_verified = true WHERE id = $1", user.id)
@@ -174,6 +202,83 @@ pub async fn verify_otp(
     }))
 }
 
+/// Resend the email verification code
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/resend-otp",
+    request_body = ResendOtpRequest,
+    responses(
+        (status = 200, description = "A new code has been sent", body = ResendOtpResponse),
+        (status = 400, description = "Invalid input or email already verified"),
+        (status = 404, description = "User not found"),
+        (status = 429, description = "Cooldown or daily limit reached"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication"
+)]
+pub async fn resend_otp(
+    State(state): State<AppState>,
+    Json(mut payload): Json<ResendOtpRequest>,
+) -> Result<Json<ResendOtpResponse>, AppError> {
+    // Normalize input
+    payload.email = normalize_email(&payload.email);
+
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Find user by email
+    let user = sqlx::query!(
+        "SELECT id, is_verified, locale FROM users WHERE email = $1",
+        payload.email
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::UserNotFound)?;
+
+    if user.is_verified.unwrap_or(false) {
+        return Err(AppError::ValidationError(
+            "Email is already verified".to_string(),
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+    check_otp_send_limit(
+        &mut tx,
+        user.id,
+        OtpPurpose::Verification,
+        state.config.otp_resend_cooldown_secs,
+        state.config.otp_daily_limit,
+    )
+    .await?;
+
+    // Older codes stop working once a new one is issued
+    invalidate_otps(&mut tx, user.id, OtpPurpose::Verification).await?;
+    let otp_code = create_otp(
+        &mut tx,
+        user.id,
+        OtpPurpose::Verification,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
+    let email_id = queue_otp_email(
+        &mut tx,
+        user.id,
+        &payload.email,
+        state.config.email_locale(user.locale.as_deref()),
+        &otp_code,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(ResendOtpResponse {
+        email_id,
+        message: "A new verification code has been sent to your email.".to_string(),
+    }))
+}
+
 /// User login
 #[utoipa::path(
     post,
@@ -181,29 +286,54 @@ pub async fn verify_otp(
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = LoginResponse),
+        (status = 202, description = "Password accepted, 2FA code required", body = TwoFactorChallengeResponse),
         (status = 401, description = "Invalid credentials"),
         (status = 400, description = "Email not verified"),
+        (status = 429, description = "Too many failed attempts, see Retry-After"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Authentication"
 )]
 pub async fn login(
     State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<LoginResponse>, AppError> {
+) -> Result<Response, AppError> {
     // Validate input
     payload
         .validate()
         .map_err(|e| AppError::ValidationError(e.to_string()))?;
 
-    // Find user by username
+    // Find user by email or username, ignoring case
+    let identifier = payload.identifier.trim().to_lowercase();
     let user = sqlx::query!(
-        "SELECT id, password_hash, is_verified FROM users WHERE username = $1",
-        payload.username
+        r#"
+        SELECT id, password_hash, is_verified, totp_enabled_at FROM users
+        WHERE ($2 AND email = $1) OR (NOT $2 AND LOWER(username) = $1)
+        "#,
+        identifier,
+        is_email_identifier(&identifier)
     )
     .fetch_optional(&state.db)
-    .await?
-    .ok_or(AppError::InvalidCredentials)?;
+    .await?;
+
+    // Throttle per account, whichever identifier was used, and per client IP.
+    // Unknown identifiers are hashed to keep the key within the column.
+    let account_key = match &user {
+        Some(user) => format!("login:user:{}", user.id),
+        None => format!("login:user:{}", hash_token(&identifier)),
+    };
+    let ip_key = client_ip.map(|ip| format!("login:ip:{}", ip));
+
+    check_throttle(&state.db, &account_key).await?;
+    if let Some(ip_key) = &ip_key {
+        check_throttle(&state.db, ip_key).await?;
+    }
+
+    let Some(user) = user else {
+        record_login_failure(&state, &account_key, ip_key.as_deref()).await?;
+        return Err(AppError::InvalidCredentials);
+    };
 
     // Check if user is verified
     if !user.is_verified.unwrap_or(false) {
@@ -213,22 +343,628 @@ pub async fn login(
     }
 
     // Verify password
-    let is_valid = crate::utils::password::verify_password(&payload.password, &user.password_hash)?;
+    let is_valid = verify_password(&payload.password, &user.password_hash).await?;
 
     if !is_valid {
+        record_login_failure(&state, &account_key, ip_key.as_deref()).await?;
         return Err(AppError::InvalidCredentials);
     }
 
-    // Generate JWT token
-    // TODO: Get JWT secret and expiry from config
-    let jwt_secret = "your-super-secret-jwt-key-change-in-production"; // Temporary
-    let jwt_expiry_hours = 24;
+    // The plaintext is at hand only now, so upgrade outdated hashes here
+    if needs_rehash(&user.password_hash, &state.config.argon2_params) {
+        upgrade_password_hash(&state, user.id, &payload.password, &user.password_hash).await;
+    }
 
-    let token = crate::utils::jwt::generate_token(user.id, jwt_secret, jwt_expiry_hours)?;
+    // With 2FA enabled the password only earns a challenge for the second
+    // step; the lockout is cleared once the code is accepted
+    if user.totp_enabled_at.is_some() {
+        return two_factor_challenge(&state, user.id).await;
+    }
 
-    Ok(Json(LoginResponse {
-        access_token: token,
-        token_type: "Bearer".to_string(),
-        expires_in: jwt_expiry_hours * 3600, // Convert to seconds
+    clear_throttle(&state.db, &account_key).await?;
+
+    Ok(Json(start_session(&state, user.id).await?).into_response())
+}
+
+/// Request a passwordless sign-in code
+///
+/// Like forgot-password, always answers with the same message so the response
+/// does not reveal whether an account exists for the email.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/login-code",
+    request_body = LoginCodeRequest,
+    responses(
+        (status = 200, description = "Sign-in code sent if the account exists", body = LoginCodeResponse),
+        (status = 400, description = "Invalid input")
+    ),
+    tag = "Authentication"
+)]
+pub async fn request_login_code(
+    State(state): State<AppState>,
+    Json(mut payload): Json<LoginCodeRequest>,
+) -> Result<Json<LoginCodeResponse>, AppError> {
+    // Normalize input
+    payload.email = normalize_email(&payload.email);
+
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    tokio::spawn(async move {
+        if let Err(e) = queue_login_code(&state, &payload.email).await {
+            tracing::error!("Failed to process sign-in code request: {:?}", e);
+        }
+    });
+
+    Ok(Json(LoginCodeResponse {
+        message: "If an account exists for this email, a sign-in code has been sent.".to_string(),
     }))
 }
+
+/// Create a sign-in code and queue its email, if the account exists
+async fn queue_login_code(state: &AppState, email: &str) -> Result<(), AppError> {
+    let Some(user) = sqlx::query!("SELECT id, locale FROM users WHERE email = $1", email)
+        .fetch_optional(&state.db)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let mut tx = state.db.begin().await?;
+    check_otp_send_limit(
+        &mut tx,
+        user.id,
+        OtpPurpose::Login,
+        state.config.otp_resend_cooldown_secs,
+        state.config.otp_daily_limit,
+    )
+    .await?;
+
+    // Only the newest sign-in code stays valid
+    invalidate_otps(&mut tx, user.id, OtpPurpose::Login).await?;
+    let code = create_otp(
+        &mut tx,
+        user.id,
+        OtpPurpose::Login,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
+    queue_login_code_email(
+        &mut tx,
+        user.id,
+        email,
+        state.config.email_locale(user.locale.as_deref()),
+        &code,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Sign in with an emailed code
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/login-code/verify",
+    request_body = VerifyLoginCodeRequest,
+    responses(
+        (status = 200, description = "Login successful", body = LoginResponse),
+        (status = 202, description = "Code accepted, 2FA code required", body = TwoFactorChallengeResponse),
+        (status = 400, description = "Invalid input or invalid/expired code"),
+        (status = 429, description = "Too many wrong guesses, request a new code"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication"
+)]
+pub async fn verify_login_code(
+    State(state): State<AppState>,
+    Json(mut payload): Json<VerifyLoginCodeRequest>,
+) -> Result<Response, AppError> {
+    // Normalize input
+    payload.email = normalize_email(&payload.email);
+
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Unknown emails get the same error as a wrong code
+    let user = sqlx::query!(
+        "SELECT id, is_verified, totp_enabled_at FROM users WHERE email = $1",
+        payload.email
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::InvalidOtp)?;
+
+    // Wrong guesses count against the same per-code limit as email verification
+    consume_otp(
+        &state.db,
+        user.id,
+        &payload.code,
+        OtpPurpose::Login,
+        state.config.otp_max_attempts,
+    )
+    .await?;
+
+    // The code reached the inbox, which is all email verification proves.
+    // Whoever registered the unverified account never proved they own the
+    // address, so the password they chose must stop working; the user can set
+    // a new one with forgot-password.
+    if !user.is_verified.unwrap_or(false) {
+        let password_hash = hash_password(&random_token(), &state.config.argon2_params).await?;
+        let reset = sqlx::query!(
+            r#"
+            UPDATE users SET is_verified = true, password_hash = $2, updated_at = NOW()
+            WHERE id = $1 AND is_verified IS NOT TRUE
+            "#,
+            user.id,
+            password_hash
+        )
+        .execute(&state.db)
+        .await?;
+
+        // No rows when its owner verified it concurrently
+        if reset.rows_affected() > 0 {
+            revoke_all_sessions(&state, user.id).await?;
+        }
+    }
+
+    // The emailed code replaces the password, not the second factor
+    if user.totp_enabled_at.is_some() {
+        return two_factor_challenge(&state, user.id).await;
+    }
+
+    Ok(Json(start_session(&state, user.id).await?).into_response())
+}
+
+/// Rehash a password with the current Argon2 settings after a successful login.
+///
+/// Failures are logged rather than returned; the login itself has succeeded.
+async fn upgrade_password_hash(state: &AppState, user_id: Uuid, password: &str, old_hash: &str) {
+    let new_hash = match hash_password(password, &state.config.argon2_params).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            tracing::warn!("Failed to rehash password of user {}: {:?}", user_id, e);
+            return;
+        }
+    };
+
+    // Leave the row alone if the password changed in the meantime
+    let result = sqlx::query!(
+        "UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash = $3",
+        new_hash,
+        user_id,
+        old_hash
+    )
+    .execute(&state.db)
+    .await;
+
+    match result {
+        Ok(_) => tracing::info!("Upgraded password hash of user {}", user_id),
+        Err(e) => tracing::warn!(
+            "Failed to store rehashed password of user {}: {}",
+            user_id,
+            e
+        ),
+    }
+}
+
+/// Count a failed login against the account and the client IP
+pub async fn record_login_failure(
+    state: &AppState,
+    account_key: &str,
+    ip_key: Option<&str>,
+) -> Result<(), AppError> {
+    let config = &state.config;
+
+    record_failure(
+        &state.db,
+        account_key,
+        LockoutPolicy {
+            max_failures: config.login_max_failures,
+            base_secs: config.login_lockout_base_secs,
+            max_secs: config.login_lockout_max_secs,
+        },
+    )
+    .await?;
+
+    if let Some(ip_key) = ip_key {
+        record_failure(
+            &state.db,
+            ip_key,
+            LockoutPolicy {
+                max_failures: config.login_ip_max_failures,
+                base_secs: config.login_lockout_base_secs,
+                max_secs: config.login_lockout_max_secs,
+            },
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Exchange a refresh token for a new token pair
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/refresh",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "Tokens refreshed", body = LoginResponse),
+        (status = 401, description = "Invalid, expired or reused refresh token"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication"
+)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Rotate: the presented token is spent and a successor is issued
+    let rotated = rotate_refresh_token(
+        &state.db,
+        &payload.refresh_token,
+        state.config.refresh_token_ttl_days,
+    )
+    .await?;
+
+    Ok(Json(token_response(
+        &state,
+        rotated.user_id,
+        rotated.refresh_token,
+    )?))
+}
+
+/// Request a password reset code
+///
+/// Always answers with the same message so the response does not reveal
+/// whether an account exists for the email; the work runs in the background.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/forgot-password",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (status = 200, description = "Reset code sent if the account exists", body = ForgotPasswordResponse),
+        (status = 400, description = "Invalid input")
+    ),
+    tag = "Authentication"
+)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(mut payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<ForgotPasswordResponse>, AppError> {
+    // Normalize input
+    payload.email = normalize_email(&payload.email);
+
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    tokio::spawn(async move {
+        if let Err(e) = queue_reset_code(&state, &payload.email).await {
+            tracing::error!("Failed to process password reset request: {:?}", e);
+        }
+    });
+
+    Ok(Json(ForgotPasswordResponse {
+        message: "If an account exists for this email, a password reset code has been sent."
+            .to_string(),
+    }))
+}
+
+/// Create a reset code and queue its email, if the account exists
+async fn queue_reset_code(state: &AppState, email: &str) -> Result<(), AppError> {
+    let Some(user) = sqlx::query!("SELECT id, locale FROM users WHERE email = $1", email)
+        .fetch_optional(&state.db)
+        .await?
+    else {
+        return Ok(());
+    };
+
+    let mut tx = state.db.begin().await?;
+    check_otp_send_limit(
+        &mut tx,
+        user.id,
+        OtpPurpose::PasswordReset,
+        state.config.otp_resend_cooldown_secs,
+        state.config.otp_daily_limit,
+    )
+    .await?;
+
+    // Only the newest reset code stays valid
+    invalidate_otps(&mut tx, user.id, OtpPurpose::PasswordReset).await?;
+    let code = create_otp(
+        &mut tx,
+        user.id,
+        OtpPurpose::PasswordReset,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
+    queue_password_reset_email(
+        &mut tx,
+        user.id,
+        email,
+        state.config.email_locale(user.locale.as_deref()),
+        &code,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Reset password with an emailed code
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/reset-password",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password reset, all sessions revoked", body = ResetPasswordResponse),
+        (status = 400, description = "Invalid input or invalid/expired code"),
+        (status = 429, description = "Too many wrong guesses, request a new code"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication"
+)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(mut payload): Json<ResetPasswordRequest>,
+) -> Result<Json<ResetPasswordResponse>, AppError> {
+    // Normalize input
+    payload.email = normalize_email(&payload.email);
+
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Check password strength before the lookup, so a weak password
+    // does not reveal whether the email belongs to an account
+    let policy = &state.config.password_policy;
+    policy.check(&payload.new_password, &[&payload.email])?;
+
+    // Unknown emails get the same error as a wrong code
+    let user = sqlx::query!(
+        "SELECT id, username FROM users WHERE email = $1",
+        payload.email
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::InvalidOtp)?;
+
+    policy.check(&payload.new_password, &[&user.username, &payload.email])?;
+
+    let password_hash = hash_password(&payload.new_password, &state.config.argon2_params).await?;
+
+    // Consume the code, then store the new password
+    consume_otp(
+        &state.db,
+        user.id,
+        &payload.code,
+        OtpPurpose::PasswordReset,
+        state.config.otp_max_attempts,
+    )
+    .await?;
+
+    sqlx::query!(
+        "UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2",
+        password_hash,
+        user.id
+    )
+    .execute(&state.db)
+    .await?;
+
+    // Sign out everywhere; whoever knew the old password loses access
+    revoke_all_sessions(&state, user.id).await?;
+
+    Ok(Json(ResetPasswordResponse {
+        message: "Password reset successfully. Please log in with your new password.".to_string(),
+    }))
+}
+
+/// Change the password of the signed-in account
+///
+/// Every other session is signed out; the response carries a fresh token
+/// pair that keeps the current client signed in.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/change-password",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 200, description = "Password changed, other sessions revoked", body = LoginResponse),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Unauthorized or wrong current password"),
+        (status = 429, description = "Too many failed attempts, see Retry-After"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication",
+    security(("bearer" = []))
+)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Wrong guesses count against the same lockout as login
+    let account_key = format!("login:user:{}", user_id);
+    check_throttle(&state.db, &account_key).await?;
+
+    let user = sqlx::query!(
+        "SELECT username, email, password_hash FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    if !verify_password(&payload.current_password, &user.password_hash).await? {
+        record_login_failure(&state, &account_key, None).await?;
+        return Err(AppError::InvalidCredentials);
+    }
+
+    if payload.new_password == payload.current_password {
+        return Err(AppError::ValidationError(
+            "The new password must differ from the current one".to_string(),
+        ));
+    }
+
+    state
+        .config
+        .password_policy
+        .check(&payload.new_password, &[&user.username, &user.email])?;
+
+    let password_hash = hash_password(&payload.new_password, &state.config.argon2_params).await?;
+    sqlx::query!(
+        "UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2",
+        password_hash,
+        user_id
+    )
+    .execute(&state.db)
+    .await?;
+
+    clear_throttle(&state.db, &account_key).await?;
+
+    // Sign out everywhere, then start a new session for this client
+    revoke_all_sessions(&state, user_id).await?;
+
+    Ok(Json(start_session(&state, user_id).await?))
+}
+
+/// Logout of the current session
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout",
+    request_body = LogoutRequest,
+    responses(
+        (status = 200, description = "Logged out", body = LogoutResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication",
+    security(("bearer" = []))
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(claims): Extension<Claims>,
+    payload: Option<Json<LogoutRequest>>,
+) -> Result<Json<LogoutResponse>, AppError> {
+    let Json(payload) = payload.unwrap_or_default();
+
+    // Revoke the access token used for this request
+    state
+        .revocations
+        .revoke(&state.db, user_id, &claims)
+        .await?;
+
+    // Revoke the session's refresh token family, if provided
+    if let Some(refresh_token) = payload.refresh_token {
+        revoke_refresh_family(&state.db, user_id, &refresh_token).await?;
+    }
+
+    // Close WebSocket connections opened with this token
+    state.ws_manager.disconnect_token(claims.jti).await;
+
+    Ok(Json(LogoutResponse {
+        message: "Logged out successfully".to_string(),
+    }))
+}
+
+/// Logout of all sessions on all devices
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout-all",
+    responses(
+        (status = 200, description = "All sessions logged out", body = LogoutResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication",
+    security(("bearer" = []))
+)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Json<LogoutResponse>, AppError> {
+    revoke_all_sessions(&state, user_id).await?;
+
+    Ok(Json(LogoutResponse {
+        message: "Logged out of all sessions".to_string(),
+    }))
+}
+
+/// Public keys for verifying access tokens
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "JSON Web Key Set; empty when tokens are signed with HS256")
+    ),
+    tag = "Authentication"
+)]
+pub async fn jwks(State(state): State<AppState>) -> impl IntoResponse {
+    let keys = state.config.jwt_keys.jwks().clone();
+
+    ([(header::CACHE_CONTROL, "public, max-age=300")], Json(keys))
+}
+
+/// Revoke every access and refresh token of a user and close their WebSocket connections
+pub async fn revoke_all_sessions(state: &AppState, user_id: Uuid) -> Result<(), AppError> {
+    state.revocations.revoke_all(&state.db, user_id).await?;
+    revoke_all_refresh_tokens(&state.db, user_id).await?;
+    state.ws_manager.disconnect_user(user_id).await;
+
+    tracing::info!("Revoked all sessions of user {}", user_id);
+    Ok(())
+}
+
+/// Start a new session: a new refresh token family and an access token.
+///
+/// Every way of logging in ends here, so this also cancels a scheduled
+/// account deletion.
+pub async fn start_session(state: &AppState, user_id: Uuid) -> Result<LoginResponse, AppError> {
+    let mut conn = state.db.acquire().await?;
+    cancel_account_deletion(&mut conn, user_id).await?;
+    let refresh_token = create_refresh_token(
+        &mut conn,
+        user_id,
+        None,
+        state.config.refresh_token_ttl_days,
+    )
+    .await?;
+
+    token_response(state, user_id, refresh_token)
+}
+
+/// Build a login response with a fresh access token
+fn token_response(
+    state: &AppState,
+    user_id: Uuid,
+    refresh_token: String,
+) -> Result<LoginResponse, AppError> {
+    let expires_in = state.config.access_token_ttl_secs;
+    let access_token = generate_token(user_id, &state.config.jwt_keys, expires_in)?;
+
+    Ok(LoginResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in,
+        refresh_token,
+        refresh_expires_in: state.config.refresh_token_ttl_days * 24 * 3600,
+    })
+}
@@ -0,0 +1,154 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    errors::AppError,
+    models::{
+        app_state::AppState,
+        data_export::{
+            DataExportDownloadQuery, DataExportRequestedResponse, DataExportStatusResponse,
+        },
+    },
+    utils::{data_export::create_data_export, refresh_token::hash_token},
+};
+
+/// Request an export of your personal data
+///
+/// The archive is built in the background; poll the status endpoint and
+/// download it with the returned token once it is ready.
+#[utoipa::path(
+    post,
+    path = "/api/v1/profile/exports",
+    responses(
+        (status = 202, description = "Export queued", body = DataExportRequestedResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 429, description = "An export was requested recently, see Retry-After"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Profile",
+    security(("bearer" = []))
+)]
+pub async fn request_data_export(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<(StatusCode, Json<DataExportRequestedResponse>), AppError> {
+    let mut tx = state.db.begin().await?;
+    let (id, download_token) = create_data_export(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    tracing::info!("User {} requested data export {}", user_id, id);
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(DataExportRequestedResponse {
+            id,
+            download_token,
+            message: "Your data export is being prepared.".to_string(),
+        }),
+    ))
+}
+
+/// Get the status of a data export
+#[utoipa::path(
+    get,
+    path = "/api/v1/profile/exports/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Export identifier returned when it was requested")
+    ),
+    responses(
+        (status = 200, description = "Export status", body = DataExportStatusResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Export not found or expired"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Profile",
+    security(("bearer" = []))
+)]
+pub async fn get_data_export(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DataExportStatusResponse>, AppError> {
+    let status = sqlx::query_as!(
+        DataExportStatusResponse,
+        r#"
+        SELECT id, status, created_at, completed_at, expires_at
+        FROM data_exports
+        WHERE id = $1 AND user_id = $2
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Export not found".to_string()))?;
+
+    Ok(Json(status))
+}
+
+/// Download a data export
+///
+/// Needs no bearer token, so the link can be opened in a browser; the
+/// download token is the credential.
+#[utoipa::path(
+    get,
+    path = "/api/v1/exports/download",
+    params(DataExportDownloadQuery),
+    responses(
+        (status = 200, description = "Zip archive of JSON files", content_type = "application/zip"),
+        (status = 401, description = "Invalid or expired token"),
+        (status = 404, description = "Export not ready yet"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Profile"
+)]
+pub async fn download_data_export(
+    State(state): State<AppState>,
+    Query(query): Query<DataExportDownloadQuery>,
+) -> Result<Response, AppError> {
+    let export = sqlx::query!(
+        "SELECT status, archive, created_at, expires_at FROM data_exports WHERE token_hash = $1",
+        hash_token(&query.token)
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::InvalidToken)?;
+
+    if export
+        .expires_at
+        .is_some_and(|expires_at| expires_at < Utc::now())
+    {
+        return Err(AppError::TokenExpired);
+    }
+
+    let archive = match (export.status.as_str(), export.archive) {
+        ("ready", Some(archive)) => archive,
+        ("failed", _) => {
+            return Err(AppError::NotFound(
+                "The export failed, please request a new one".to_string(),
+            ));
+        }
+        _ => return Err(AppError::NotFound("Export is not ready yet".to_string())),
+    };
+
+    let disposition = format!(
+        "attachment; filename=\"spot-feed-export-{}.zip\"",
+        export.created_at.format("%Y-%m-%d")
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+        archive,
+    )
+        .into_response())
+}
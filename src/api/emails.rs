@@ -0,0 +1,44 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use uuid::Uuid;
+
+use crate::{
+    errors::AppError,
+    models::{app_state::AppState, email::EmailStatusResponse},
+};
+
+/// Get the delivery status of a queued email
+#[utoipa::path(
+    get,
+    path = "/api/v1/emails/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Email identifier returned when it was queued")
+    ),
+    responses(
+        (status = 200, description = "Delivery status", body = EmailStatusResponse),
+        (status = 404, description = "Email not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Emails"
+)]
+pub async fn get_email_status(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<EmailStatusResponse>, AppError> {
+    let status = sqlx::query_as!(
+        EmailStatusResponse,
+        r#"
+        SELECT id, status
+        FROM email_outbox
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Email not found".to_string()))?;
+
+    Ok(Json(status))
+}
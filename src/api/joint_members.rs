@@ -0,0 +1,525 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use chrono::{Duration, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    api::joints::ensure_open,
+    errors::AppError,
+    models::{
+        app_state::AppState,
+        joint::{
+            BanMemberRequest, BanResponse, Joint, JointActionResponse, JointMemberRequest,
+            JointRole, RoleChangeResponse,
+        },
+    },
+    utils::joint_permissions::{JointAction, authorize, outranks},
+    ws::events::JointEvent,
+};
+
+/// Hand the creator role to another member
+///
+/// The previous creator stays on as a moderator.
+#[utoipa::path(
+    post,
+    path = "/api/v1/joints/{id}/transfer",
+    params(("id" = Uuid, Path, description = "Joint ID")),
+    request_body = JointMemberRequest,
+    responses(
+        (status = 200, description = "Ownership transferred", body = RoleChangeResponse),
+        (status = 400, description = "Joint closed or already yours"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only the creator can transfer the joint"),
+        (status = 404, description = "Joint not found or user not a member"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn transfer_ownership(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(joint_id): Path<Uuid>,
+    Json(payload): Json<JointMemberRequest>,
+) -> Result<Json<RoleChangeResponse>, AppError> {
+    let new_creator = payload.user_id;
+    if new_creator == user_id {
+        return Err(AppError::ValidationError(
+            "You already own this joint".to_string(),
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+    lock_joint(&mut tx, joint_id, user_id, JointAction::TransferOwnership).await?;
+    target_role(&mut tx, joint_id, new_creator).await?;
+
+    set_role(&mut tx, joint_id, user_id, JointRole::Moderator).await?;
+    set_role(&mut tx, joint_id, new_creator, JointRole::Creator).await?;
+    sqlx::query!(
+        "UPDATE joints SET creator_id = $1 WHERE id = $2",
+        new_creator,
+        joint_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!(
+        "User {} transferred joint {} to user {}",
+        user_id,
+        joint_id,
+        new_creator
+    );
+    announce_role(&state, joint_id, user_id, JointRole::Moderator, user_id).await;
+    announce_role(&state, joint_id, new_creator, JointRole::Creator, user_id).await;
+
+    Ok(Json(RoleChangeResponse {
+        user_id: new_creator,
+        role: JointRole::Creator,
+        message: "Ownership transferred".to_string(),
+    }))
+}
+
+/// Make a member a moderator
+#[utoipa::path(
+    post,
+    path = "/api/v1/joints/{id}/moderators",
+    params(("id" = Uuid, Path, description = "Joint ID")),
+    request_body = JointMemberRequest,
+    responses(
+        (status = 200, description = "Member promoted", body = RoleChangeResponse),
+        (status = 400, description = "Joint closed or user already a moderator or the creator"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only the creator can promote members"),
+        (status = 404, description = "Joint not found or user not a member"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn promote_moderator(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(joint_id): Path<Uuid>,
+    Json(payload): Json<JointMemberRequest>,
+) -> Result<Json<RoleChangeResponse>, AppError> {
+    let target = payload.user_id;
+
+    let mut tx = state.db.begin().await?;
+    lock_joint(&mut tx, joint_id, user_id, JointAction::PromoteModerator).await?;
+    if target_role(&mut tx, joint_id, target).await? != JointRole::Member {
+        return Err(AppError::ValidationError(
+            "Only regular members can be promoted".to_string(),
+        ));
+    }
+
+    set_role(&mut tx, joint_id, target, JointRole::Moderator).await?;
+    tx.commit().await?;
+
+    tracing::info!(
+        "User {} made user {} a moderator of joint {}",
+        user_id,
+        target,
+        joint_id
+    );
+    announce_role(&state, joint_id, target, JointRole::Moderator, user_id).await;
+
+    Ok(Json(RoleChangeResponse {
+        user_id: target,
+        role: JointRole::Moderator,
+        message: "Member promoted to moderator".to_string(),
+    }))
+}
+
+/// Make a moderator a regular member again
+#[utoipa::path(
+    delete,
+    path = "/api/v1/joints/{id}/moderators/{user_id}",
+    params(
+        ("id" = Uuid, Path, description = "Joint ID"),
+        ("user_id" = Uuid, Path, description = "Moderator's user ID")
+    ),
+    responses(
+        (status = 200, description = "Moderator demoted", body = RoleChangeResponse),
+        (status = 400, description = "Joint closed or user not a moderator"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only the creator can demote moderators"),
+        (status = 404, description = "Joint not found or user not a member"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn demote_moderator(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path((joint_id, target)): Path<(Uuid, Uuid)>,
+) -> Result<Json<RoleChangeResponse>, AppError> {
+    let mut tx = state.db.begin().await?;
+    lock_joint(&mut tx, joint_id, user_id, JointAction::DemoteModerator).await?;
+    if target_role(&mut tx, joint_id, target).await? != JointRole::Moderator {
+        return Err(AppError::ValidationError(
+            "User is not a moderator of this joint".to_string(),
+        ));
+    }
+
+    set_role(&mut tx, joint_id, target, JointRole::Member).await?;
+    tx.commit().await?;
+
+    tracing::info!(
+        "User {} removed user {} as moderator of joint {}",
+        user_id,
+        target,
+        joint_id
+    );
+    announce_role(&state, joint_id, target, JointRole::Member, user_id).await;
+
+    Ok(Json(RoleChangeResponse {
+        user_id: target,
+        role: JointRole::Member,
+        message: "Moderator demoted to member".to_string(),
+    }))
+}
+
+/// Remove a member from a joint; they may join again
+///
+/// Their live connection to the joint is closed.
+#[utoipa::path(
+    post,
+    path = "/api/v1/joints/{id}/kick",
+    params(("id" = Uuid, Path, description = "Joint ID")),
+    request_body = JointMemberRequest,
+    responses(
+        (status = 200, description = "Member removed", body = JointActionResponse),
+        (status = 400, description = "Joint closed or kicking yourself"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not allowed to remove this member"),
+        (status = 404, description = "Joint not found or user not a member"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn kick_member(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(joint_id): Path<Uuid>,
+    Json(payload): Json<JointMemberRequest>,
+) -> Result<Json<JointActionResponse>, AppError> {
+    let target = payload.user_id;
+    if target == user_id {
+        return Err(AppError::ValidationError(
+            "Use leave to exit a joint yourself".to_string(),
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let role = lock_joint(&mut tx, joint_id, user_id, JointAction::KickMember).await?;
+    ensure_outranks(role, target_role(&mut tx, joint_id, target).await?)?;
+
+    remove_member(&mut tx, joint_id, target).await?;
+    tx.commit().await?;
+
+    tracing::info!(
+        "User {} kicked user {} from joint {}",
+        user_id,
+        target,
+        joint_id
+    );
+    disconnect_removed(&state, joint_id, target, false, user_id).await;
+
+    Ok(Json(JointActionResponse {
+        message: "Member removed from the joint".to_string(),
+    }))
+}
+
+/// Ban a user from a joint
+///
+/// Members are removed and their live connection is closed. Until the ban
+/// expires they cannot join or connect again. Banning again replaces the
+/// reason and expiry.
+#[utoipa::path(
+    post,
+    path = "/api/v1/joints/{id}/bans",
+    params(("id" = Uuid, Path, description = "Joint ID")),
+    request_body = BanMemberRequest,
+    responses(
+        (status = 200, description = "User banned", body = BanResponse),
+        (status = 400, description = "Invalid input, joint closed or banning yourself"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not allowed to ban this member"),
+        (status = 404, description = "Joint or user not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn ban_member(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(joint_id): Path<Uuid>,
+    Json(payload): Json<BanMemberRequest>,
+) -> Result<Json<BanResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let target = payload.user_id;
+    if target == user_id {
+        return Err(AppError::ValidationError(
+            "You cannot ban yourself".to_string(),
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let role = lock_joint(&mut tx, joint_id, user_id, JointAction::BanMember).await?;
+    if let Some(target_role) = member_role(&mut tx, joint_id, target).await? {
+        ensure_outranks(role, target_role)?;
+        remove_member(&mut tx, joint_id, target).await?;
+    }
+
+    let expires_at = payload
+        .expires_in_hours
+        .map(|hours| Utc::now() + Duration::hours(hours as i64));
+
+    sqlx::query!(
+        r#"
+        INSERT INTO joint_bans (joint_id, user_id, banned_by, reason, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (joint_id, user_id) DO UPDATE
+        SET banned_by = $3, reason = $4, expires_at = $5, created_at = NOW()
+        "#,
+        joint_id,
+        target,
+        user_id,
+        payload.reason,
+        expires_at
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => AppError::UserNotFound,
+        e => AppError::DatabaseError(e),
+    })?;
+    tx.commit().await?;
+
+    tracing::info!(
+        "User {} banned user {} from joint {}",
+        user_id,
+        target,
+        joint_id
+    );
+    disconnect_removed(&state, joint_id, target, true, user_id).await;
+
+    Ok(Json(BanResponse {
+        user_id: target,
+        expires_at,
+        message: "User banned from the joint".to_string(),
+    }))
+}
+
+/// Lift a ban
+#[utoipa::path(
+    delete,
+    path = "/api/v1/joints/{id}/bans/{user_id}",
+    params(
+        ("id" = Uuid, Path, description = "Joint ID"),
+        ("user_id" = Uuid, Path, description = "Banned user's ID")
+    ),
+    responses(
+        (status = 200, description = "Ban lifted", body = JointActionResponse),
+        (status = 400, description = "Joint closed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only the creator and moderators can lift bans"),
+        (status = 404, description = "Joint not found or user not banned"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn unban_member(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path((joint_id, target)): Path<(Uuid, Uuid)>,
+) -> Result<Json<JointActionResponse>, AppError> {
+    let mut tx = state.db.begin().await?;
+    lock_joint(&mut tx, joint_id, user_id, JointAction::UnbanMember).await?;
+
+    let lifted = sqlx::query!(
+        "DELETE FROM joint_bans WHERE joint_id = $1 AND user_id = $2",
+        joint_id,
+        target
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if lifted == 0 {
+        return Err(AppError::NotFound(
+            "User is not banned from this joint".to_string(),
+        ));
+    }
+    tx.commit().await?;
+
+    tracing::info!(
+        "User {} lifted the ban of user {} from joint {}",
+        user_id,
+        target,
+        joint_id
+    );
+
+    Ok(Json(JointActionResponse {
+        message: "Ban lifted".to_string(),
+    }))
+}
+
+/// Lock an open joint for a membership change and check the caller may perform `action`.
+///
+/// Locking the joint row serializes concurrent membership changes. Returns the
+/// caller's role. Run inside a transaction.
+pub async fn lock_joint(
+    conn: &mut PgConnection,
+    joint_id: Uuid,
+    user_id: Uuid,
+    action: JointAction,
+) -> Result<JointRole, AppError> {
+    let joint = lock_joint_row(conn, joint_id).await?;
+
+    let role = authorize(member_role(conn, joint_id, user_id).await?, action)?;
+    ensure_open(&joint)?;
+
+    Ok(role)
+}
+
+/// Lock a joint row against concurrent membership changes; `NotFound` when missing
+pub async fn lock_joint_row(conn: &mut PgConnection, joint_id: Uuid) -> Result<Joint, AppError> {
+    let joint = sqlx::query_as!(
+        Joint,
+        "SELECT id, name, creator_id, joint_type, visibility, latitude, longitude, radius, created_at, expires_at, description, is_active FROM joints WHERE id = $1 FOR UPDATE",
+        joint_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Joint not found".to_string()))?;
+
+    Ok(joint)
+}
+
+/// Role of a user in a joint, None when not a member
+pub async fn member_role(
+    conn: &mut PgConnection,
+    joint_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<JointRole>, AppError> {
+    let role = sqlx::query_scalar!(
+        "SELECT role FROM joint_members WHERE joint_id = $1 AND user_id = $2",
+        joint_id,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(role.as_deref().and_then(JointRole::parse))
+}
+
+/// Role of the member an action targets; `NotFound` when they are not a member
+pub async fn target_role(
+    conn: &mut PgConnection,
+    joint_id: Uuid,
+    user_id: Uuid,
+) -> Result<JointRole, AppError> {
+    member_role(conn, joint_id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User is not a member of this joint".to_string()))
+}
+
+async fn set_role(
+    conn: &mut PgConnection,
+    joint_id: Uuid,
+    user_id: Uuid,
+    role: JointRole,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE joint_members SET role = $1 WHERE joint_id = $2 AND user_id = $3",
+        role.as_str(),
+        joint_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Fail with `Forbidden` unless `actor` ranks above `target`
+fn ensure_outranks(actor: JointRole, target: JointRole) -> Result<(), AppError> {
+    if !outranks(actor, target) {
+        return Err(AppError::Forbidden(format!(
+            "A joint {} cannot remove a {}",
+            actor.as_str(),
+            target.as_str()
+        )));
+    }
+    Ok(())
+}
+
+async fn remove_member(
+    conn: &mut PgConnection,
+    joint_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "DELETE FROM joint_members WHERE joint_id = $1 AND user_id = $2",
+        joint_id,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Tell the joint a member was removed, then close the member's connection.
+///
+/// The event is queued before the close, so the removed member receives it too.
+pub async fn disconnect_removed(
+    state: &AppState,
+    joint_id: Uuid,
+    user_id: Uuid,
+    banned: bool,
+    removed_by: Uuid,
+) {
+    let event = JointEvent::MemberRemoved {
+        joint_id,
+        user_id,
+        banned,
+        removed_by,
+    };
+    state.ws_manager.send_event(joint_id, &event).await;
+    state
+        .ws_manager
+        .disconnect_from_joint(user_id, joint_id)
+        .await;
+}
+
+/// Tell the joint's connected clients about a role change
+async fn announce_role(
+    state: &AppState,
+    joint_id: Uuid,
+    user_id: Uuid,
+    role: JointRole,
+    changed_by: Uuid,
+) {
+    let event = JointEvent::RoleChanged {
+        joint_id,
+        user_id,
+        role,
+        changed_by,
+    };
+    state.ws_manager.send_event(joint_id, &event).await;
+}
@@ -1,16 +1,28 @@
-use axum::{Extension, Json, extract::State, http::StatusCode};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use sqlx::PgPool;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    api::joint_members::{disconnect_removed, lock_joint_row, member_role},
     errors::AppError,
     models::{
         app_state::AppState,
         joint::{
             CreateJointRequest, CreateJointResponse, JoinJointRequest, JoinJointResponse, Joint,
-            JointWithDistance, ListJointsRequest, ListJointsResponse,
+            JointActionResponse, JointDetailResponse, JointRole, JointWithDistance,
+            ListJointsRequest, ListJointsResponse, UpdateJointRequest,
         },
     },
+    utils::{
+        joint_bans::ensure_not_banned,
+        joint_permissions::{JointAction, authorize},
+    },
 };
 
 /// Create a new joint
@@ -175,8 +187,9 @@ pub async fn list_nearby_joints(
     request_body = JoinJointRequest,
     responses(
         (status = 200, description = "Successfully joined joint", body = JoinJointResponse),
-        (status = 400, description = "Invalid input or already a member"),
+        (status = 400, description = "Invalid input, joint closed or already a member"),
         (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Banned from the joint"),
         (status = 404, description = "Joint not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -188,17 +201,13 @@ pub async fn join_joint(
     Extension(user_id): Extension<Uuid>,
     Json(payload): Json<JoinJointRequest>,
 ) -> Result<Json<JoinJointResponse>, AppError> {
-    // Check if joint exists and is active
-    let _joint = sqlx::query_as!(
-        Joint,
-        "SELECT id, name, creator_id, joint_type, visibility, latitude, longitude, radius, created_at, expires_at, description, is_active FROM joints WHERE id = $1",
-        payload.joint_id
-    )
-    .fetch_optional(&state.db)
-    .await?
-    .ok_or(AppError::ValidationError(
-        "Joint not found or inactive".to_string(),
-    ))?;
+    // Check if joint exists and is active; the lock keeps a ban from
+    // landing between the ban check and the insert
+    let mut tx = state.db.begin().await?;
+    let joint = lock_joint_row(&mut tx, payload.joint_id).await?;
+
+    ensure_open(&joint)?;
+    ensure_not_banned(&mut tx, joint.id, user_id).await?;
 
     // Check if already a member
     let existing_member = sqlx::query!(
@@ -206,7 +215,7 @@ pub async fn join_joint(
         payload.joint_id,
         user_id
     )
-    .fetch_optional(&state.db)
+    .fetch_optional(&mut *tx)
     .await?;
 
     if existing_member.is_some() {
@@ -224,8 +233,9 @@ pub async fn join_joint(
         payload.joint_id,
         user_id
     )
-    .execute(&state.db)
+    .execute(&mut *tx)
     .await?;
+    tx.commit().await?;
 
     Ok(Json(JoinJointResponse {
         message: "Successfully joined the joint!".to_string(),
@@ -318,20 +328,19 @@ pub async fn leave_joint(
     Extension(user_id): Extension<Uuid>,
     Json(payload): Json<JoinJointRequest>,
 ) -> Result<Json<JoinJointResponse>, AppError> {
+    // Lock the joint so an ownership transfer cannot hand it to a leaving member
+    let mut tx = state.db.begin().await?;
+    lock_joint_row(&mut tx, payload.joint_id).await?;
+
     // Check if user is a member
-    let member = sqlx::query!(
-        "SELECT id, role FROM joint_members WHERE joint_id = $1 AND user_id = $2",
-        payload.joint_id,
-        user_id
-    )
-    .fetch_optional(&state.db)
-    .await?
-    .ok_or(AppError::ValidationError(
-        "You are not a member of this joint".to_string(),
-    ))?;
+    let role = member_role(&mut tx, payload.joint_id, user_id)
+        .await?
+        .ok_or(AppError::ValidationError(
+            "You are not a member of this joint".to_string(),
+        ))?;
 
     // If user is creator, they cannot leave (must transfer admin or delete joint)
-    if member.role == "creator" {
+    if role == JointRole::Creator {
         return Err(AppError::ValidationError(
             "Creators cannot leave joints. Please transfer admin rights or delete the joint."
                 .to_string(),
@@ -344,11 +353,232 @@ pub async fn leave_joint(
         payload.joint_id,
         user_id
     )
-    .execute(&state.db)
+    .execute(&mut *tx)
     .await?;
+    tx.commit().await?;
+
+    disconnect_removed(&state, payload.joint_id, user_id, false, user_id).await;
 
     Ok(Json(JoinJointResponse {
         message: "Successfully left the joint!".to_string(),
         joined: false,
     }))
 }
+
+/// Get a joint
+#[utoipa::path(
+    get,
+    path = "/api/v1/joints/{id}",
+    params(("id" = Uuid, Path, description = "Joint ID")),
+    responses(
+        (status = 200, description = "Joint details", body = JointDetailResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Joint not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn get_joint(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(joint_id): Path<Uuid>,
+) -> Result<Json<JointDetailResponse>, AppError> {
+    Ok(Json(joint_detail(&state.db, joint_id, user_id).await?))
+}
+
+/// Update a joint's name, description or radius
+#[utoipa::path(
+    patch,
+    path = "/api/v1/joints/{id}",
+    params(("id" = Uuid, Path, description = "Joint ID")),
+    request_body = UpdateJointRequest,
+    responses(
+        (status = 200, description = "Joint updated", body = JointDetailResponse),
+        (status = 400, description = "Invalid input or joint closed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only the creator and moderators can edit the joint"),
+        (status = 404, description = "Joint not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn update_joint(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(joint_id): Path<Uuid>,
+    Json(payload): Json<UpdateJointRequest>,
+) -> Result<Json<JointDetailResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    if payload.name.is_none() && payload.description.is_none() && payload.radius.is_none() {
+        return Err(AppError::ValidationError("Nothing to update".to_string()));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let joint = lock_joint_row(&mut tx, joint_id).await?;
+    authorize(
+        member_role(&mut tx, joint_id, user_id).await?,
+        JointAction::EditJoint,
+    )?;
+    ensure_open(&joint)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE joints
+        SET name = COALESCE($1, name),
+            description = CASE WHEN $2::text IS NULL THEN description ELSE NULLIF($2, '') END,
+            radius = COALESCE($3, radius)
+        WHERE id = $4
+        "#,
+        payload.name,
+        payload.description,
+        payload.radius,
+        joint_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(joint_detail(&state.db, joint_id, user_id).await?))
+}
+
+/// Close a joint
+///
+/// The joint and its messages are kept but nobody can join or chat any more;
+/// connected clients are disconnected.
+#[utoipa::path(
+    post,
+    path = "/api/v1/joints/{id}/close",
+    params(("id" = Uuid, Path, description = "Joint ID")),
+    responses(
+        (status = 200, description = "Joint closed", body = JointActionResponse),
+        (status = 400, description = "Joint already closed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only the creator can close the joint"),
+        (status = 404, description = "Joint not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn close_joint(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(joint_id): Path<Uuid>,
+) -> Result<Json<JointActionResponse>, AppError> {
+    let mut tx = state.db.begin().await?;
+    let joint = lock_joint_row(&mut tx, joint_id).await?;
+    authorize(
+        member_role(&mut tx, joint_id, user_id).await?,
+        JointAction::CloseJoint,
+    )?;
+
+    if joint.is_active == Some(false) {
+        return Err(AppError::ValidationError(
+            "Joint is already closed".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        "UPDATE joints SET is_active = false WHERE id = $1",
+        joint_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    state.ws_manager.disconnect_joint(joint_id).await;
+    tracing::info!("User {} closed joint {}", user_id, joint_id);
+
+    Ok(Json(JointActionResponse {
+        message: "Joint closed".to_string(),
+    }))
+}
+
+/// Delete a joint with its memberships and messages
+#[utoipa::path(
+    delete,
+    path = "/api/v1/joints/{id}",
+    params(("id" = Uuid, Path, description = "Joint ID")),
+    responses(
+        (status = 200, description = "Joint deleted", body = JointActionResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Only the creator can delete the joint"),
+        (status = 404, description = "Joint not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Joints",
+    security(("bearer" = []))
+)]
+pub async fn delete_joint(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(joint_id): Path<Uuid>,
+) -> Result<Json<JointActionResponse>, AppError> {
+    let detail = joint_detail(&state.db, joint_id, user_id).await?;
+    authorize(detail.role, JointAction::DeleteJoint)?;
+
+    sqlx::query!("DELETE FROM joints WHERE id = $1", joint_id)
+        .execute(&state.db)
+        .await?;
+
+    state.ws_manager.disconnect_joint(joint_id).await;
+    tracing::info!("User {} deleted joint {}", user_id, joint_id);
+
+    Ok(Json(JointActionResponse {
+        message: "Joint deleted".to_string(),
+    }))
+}
+
+/// Load a joint with its member count and the caller's role
+pub async fn joint_detail(
+    db: &PgPool,
+    joint_id: Uuid,
+    user_id: Uuid,
+) -> Result<JointDetailResponse, AppError> {
+    let joint = sqlx::query_as!(
+        Joint,
+        "SELECT id, name, creator_id, joint_type, visibility, latitude, longitude, radius, created_at, expires_at, description, is_active FROM joints WHERE id = $1",
+        joint_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Joint not found".to_string()))?;
+
+    let members = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) as "member_count!",
+            MAX(role) FILTER (WHERE user_id = $2) as role
+        FROM joint_members
+        WHERE joint_id = $1
+        "#,
+        joint_id,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(JointDetailResponse {
+        joint,
+        member_count: members.member_count,
+        role: members.role.as_deref().and_then(JointRole::parse),
+    })
+}
+
+/// Fail unless the joint is still open for joining and chatting
+pub fn ensure_open(joint: &Joint) -> Result<(), AppError> {
+    let expired = joint
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now());
+
+    if joint.is_active == Some(false) || expired {
+        return Err(AppError::ValidationError("Joint is closed".to_string()));
+    }
+    Ok(())
+}
@@ -1,3 +1,8 @@
 pub mod auth;
+pub mod data_export;
+pub mod emails;
+pub mod joint_members;
 pub mod joints;
+pub mod oidc;
 pub mod profile;
+pub mod two_factor;
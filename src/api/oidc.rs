@@ -0,0 +1,369 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+};
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sqlx::PgConnection;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    api::{
+        auth::{revoke_all_sessions, start_session},
+        two_factor::two_factor_challenge,
+    },
+    errors::AppError,
+    models::{
+        app_state::AppState,
+        oidc::{OidcAuthorizeResponse, OidcCallbackRequest, OidcProvidersResponse},
+        two_factor::TwoFactorChallengeResponse,
+        user::LoginResponse,
+    },
+    oidc::{
+        id_token::IdTokenClaims,
+        pkce::{code_challenge, random_token},
+    },
+    utils::{
+        normalize::{is_email_identifier, normalize_email, normalize_username},
+        password::hash_password,
+        refresh_token::hash_token,
+    },
+};
+
+/// List the configured identity providers
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/oidc/providers",
+    responses(
+        (status = 200, description = "Configured providers", body = OidcProvidersResponse)
+    ),
+    tag = "Authentication"
+)]
+pub async fn list_oidc_providers(State(state): State<AppState>) -> Json<OidcProvidersResponse> {
+    Json(OidcProvidersResponse {
+        providers: state.oidc.provider_names(),
+    })
+}
+
+/// Start signing in with an external identity provider
+///
+/// Returns the provider's authorization URL for an authorization code flow
+/// with PKCE; the client opens it and later posts the redirect's `code` and
+/// `state` to the callback endpoint.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/oidc/{provider}/authorize",
+    params(("provider" = String, Path, description = "Provider name")),
+    responses(
+        (status = 200, description = "Authorization URL", body = OidcAuthorizeResponse),
+        (status = 404, description = "Unknown provider"),
+        (status = 500, description = "Provider unreachable or internal error")
+    ),
+    tag = "Authentication"
+)]
+pub async fn start_oidc_login(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> Result<Json<OidcAuthorizeResponse>, AppError> {
+    let login_state = random_token();
+    let nonce = random_token();
+    let code_verifier = random_token();
+
+    let authorization_url = state
+        .oidc
+        .authorization_url(
+            &provider,
+            &login_state,
+            &nonce,
+            &code_challenge(&code_verifier),
+        )
+        .await?;
+
+    let expires_in = state.config.oidc_state_ttl_secs;
+    let mut tx = state.db.begin().await?;
+
+    // Abandoned sign-ins are swept whenever a new one starts
+    sqlx::query!("DELETE FROM oidc_login_states WHERE expires_at < NOW()")
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO oidc_login_states (provider, state_hash, nonce, code_verifier, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        provider,
+        hash_token(&login_state),
+        nonce,
+        code_verifier,
+        Utc::now() + Duration::seconds(expires_in)
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Json(OidcAuthorizeResponse {
+        authorization_url,
+        state: login_state,
+        expires_in,
+    }))
+}
+
+/// Finish signing in with an external identity provider
+///
+/// The identity is matched to a linked account, else linked to the account
+/// with the same (provider-verified) email, else a new account is created.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/oidc/{provider}/callback",
+    params(("provider" = String, Path, description = "Provider name")),
+    request_body = OidcCallbackRequest,
+    responses(
+        (status = 200, description = "Login successful", body = LoginResponse),
+        (status = 202, description = "Identity accepted, 2FA code required", body = TwoFactorChallengeResponse),
+        (status = 400, description = "Invalid input, no verified email or 18+ confirmation missing"),
+        (status = 401, description = "Invalid or expired state, code or ID token"),
+        (status = 404, description = "Unknown provider"),
+        (status = 500, description = "Provider unreachable or internal error")
+    ),
+    tag = "Authentication"
+)]
+pub async fn complete_oidc_login(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    Json(payload): Json<OidcCallbackRequest>,
+) -> Result<Response, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    if !state.oidc.has_provider(&provider) {
+        return Err(AppError::NotFound(format!(
+            "Unknown identity provider '{}'",
+            provider
+        )));
+    }
+
+    // The state is single use, whatever the outcome
+    let pending = sqlx::query!(
+        r#"
+        DELETE FROM oidc_login_states
+        WHERE state_hash = $1 AND provider = $2
+        RETURNING nonce, code_verifier, expires_at
+        "#,
+        hash_token(&payload.state),
+        provider
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::InvalidToken)?;
+
+    if pending.expires_at < Utc::now() {
+        return Err(AppError::TokenExpired);
+    }
+
+    let claims = state
+        .oidc
+        .exchange_code(
+            &provider,
+            &payload.code,
+            &pending.code_verifier,
+            &pending.nonce,
+        )
+        .await?;
+
+    let user_id = resolve_user(&state, &provider, &claims, &payload).await?;
+
+    let totp_enabled_at =
+        sqlx::query_scalar!("SELECT totp_enabled_at FROM users WHERE id = $1", user_id)
+            .fetch_one(&state.db)
+            .await?;
+
+    // The provider replaces the password, not the second factor
+    if totp_enabled_at.is_some() {
+        return two_factor_challenge(&state, user_id).await;
+    }
+
+    Ok(Json(start_session(&state, user_id).await?).into_response())
+}
+
+/// Find or create the user an external identity signs in as
+async fn resolve_user(
+    state: &AppState,
+    provider: &str,
+    claims: &IdTokenClaims,
+    payload: &OidcCallbackRequest,
+) -> Result<Uuid, AppError> {
+    let email = claims.verified_email().map(normalize_email);
+
+    // Already linked
+    let linked = sqlx::query_scalar!(
+        r#"
+        UPDATE oidc_identities SET last_login_at = NOW(), email = COALESCE($3, email)
+        WHERE provider = $1 AND subject = $2
+        RETURNING user_id
+        "#,
+        provider,
+        claims.sub,
+        email
+    )
+    .fetch_optional(&state.db)
+    .await?;
+
+    if let Some(user_id) = linked {
+        return Ok(user_id);
+    }
+
+    // Linking by email is only safe when the provider vouches for the address
+    let email = email.ok_or_else(|| {
+        AppError::ValidationError(
+            "The identity provider did not share a verified email address".to_string(),
+        )
+    })?;
+
+    let existing = sqlx::query!("SELECT id, is_verified FROM users WHERE email = $1", email)
+        .fetch_optional(&state.db)
+        .await?;
+
+    let mut tx;
+    let mut unverified = false;
+    let user_id = match existing {
+        Some(user) if user.is_verified == Some(true) => {
+            tx = state.db.begin().await?;
+            user.id
+        }
+        Some(user) => {
+            // Whoever registered this unverified account never proved they own
+            // the address, so the password they chose must stop working. The
+            // user can set a new one with forgot-password; hashed before the
+            // transaction starts.
+            let password_hash = hash_password(&random_token(), &state.config.argon2_params).await?;
+
+            tx = state.db.begin().await?;
+            let reset = sqlx::query!(
+                r#"
+                UPDATE users SET is_verified = true, password_hash = $2, updated_at = NOW()
+                WHERE id = $1 AND is_verified IS NOT TRUE
+                "#,
+                user.id,
+                password_hash
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            // No rows when its owner verified it concurrently
+            unverified = reset.rows_affected() > 0;
+            user.id
+        }
+        None => {
+            if payload.is_18_plus != Some(true) {
+                return Err(AppError::ValidationError(
+                    "You must be 18 or older to register".to_string(),
+                ));
+            }
+
+            // A random password until the user sets one with forgot-password;
+            // hashed before the transaction starts
+            let password_hash = hash_password(&random_token(), &state.config.argon2_params).await?;
+
+            tx = state.db.begin().await?;
+            let username = available_username(&mut tx, claims, &email).await?;
+            sqlx::query_scalar!(
+                r#"
+                INSERT INTO users (username, email, password_hash, is_18_plus, is_verified, locale)
+                VALUES ($1, $2, $3, true, true, $4)
+                RETURNING id
+                "#,
+                username,
+                email,
+                password_hash,
+                payload.locale
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| match e {
+                // Lost a race against a concurrent registration
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    AppError::UserAlreadyExists
+                }
+                e => AppError::DatabaseError(e),
+            })?
+        }
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO oidc_identities (user_id, provider, subject, email)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        provider,
+        claims.sub,
+        email
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| match e {
+        // The same identity completed a second sign-in concurrently
+        sqlx::Error::Database(db) if db.is_unique_violation() => AppError::UserAlreadyExists,
+        e => AppError::DatabaseError(e),
+    })?;
+    tx.commit().await?;
+
+    if unverified {
+        revoke_all_sessions(state, user_id).await?;
+    }
+
+    tracing::info!(
+        "Linked {} identity {} to user {}",
+        provider,
+        claims.sub,
+        user_id
+    );
+    Ok(user_id)
+}
+
+/// Pick a free username from the ID token, falling back to the email's local part
+async fn available_username(
+    conn: &mut PgConnection,
+    claims: &IdTokenClaims,
+    email: &str,
+) -> Result<String, AppError> {
+    let local_part = email.split('@').next().unwrap_or_default();
+    let source = claims
+        .preferred_username
+        .as_deref()
+        .filter(|name| !is_email_identifier(name))
+        .unwrap_or(local_part);
+    let mut base: String = normalize_username(source)
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        .take(40)
+        .collect();
+    if base.chars().count() < 3 {
+        base = format!("user{}", base);
+    }
+
+    let mut candidate = base.clone();
+    for _ in 0..10 {
+        let taken = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM users WHERE LOWER(username) = LOWER($1)) as "taken!""#,
+            candidate
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if !taken {
+            return Ok(candidate);
+        }
+        candidate = format!("{}_{}", base, rand::rng().random_range(1000..10000));
+    }
+
+    Err(AppError::InternalError(format!(
+        "No free username found for '{}'",
+        base
+    )))
+}
@@ -1,12 +1,29 @@
-use axum::{Extension, Json, extract::State};
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use chrono::{Duration, Utc};
+use serde_json::json;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    api::auth::{record_login_failure, revoke_all_sessions},
     errors::AppError,
     models::{
         app_state::AppState,
-        profile::{ProfileResponse, UpdateProfileRequest, UserProfile},
+        profile::{
+            ChangeEmailRequest, ChangeEmailResponse, DeleteAccountRequest, DeleteAccountResponse,
+            ProfileResponse, UpdateProfileRequest, UserProfile, VerifyEmailChangeRequest,
+        },
+    },
+    utils::{
+        audit::{AuditEvent, record_audit_event},
+        email::{
+            queue_account_deletion_email, queue_email_change_email, queue_email_change_notice,
+        },
+        normalize::normalize_email,
+        otp::{OtpPurpose, check_otp_send_limit, consume_otp, create_otp, invalidate_otps},
+        password::verify_password,
+        throttle::{check_throttle, clear_throttle},
+        two_factor::check_second_factor,
     },
 };
 
@@ -29,7 +46,7 @@ pub async fn get_profile(
     let profile = sqlx::query_as!(
         UserProfile,
         r#"
-        SELECT id, username, email, display_name, profile_picture_url, phone_number, created_at
+        SELECT id, username, email, display_name, profile_picture_url, phone_number, locale, pending_email, created_at
         FROM users
         WHERE id = $1
         "#,
@@ -73,17 +90,318 @@ pub async fn update_profile(
         SET 
             display_name = COALESCE($1, display_name),
             profile_picture_url = COALESCE($2, profile_picture_url),
-            phone_number = COALESCE($3, phone_number)
+            phone_number = COALESCE($3, phone_number),
+            locale = COALESCE($5, locale)
         WHERE id = $4
-        RETURNING id, username, email, display_name, profile_picture_url, phone_number, created_at
+        RETURNING id, username, email, display_name, profile_picture_url, phone_number, locale, pending_email, created_at
         "#,
         payload.display_name,
         payload.profile_picture_url,
         payload.phone_number,
+        user_id,
+        payload.locale
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Json(ProfileResponse { profile }))
+}
+
+/// Start changing the account email address
+#[utoipa::path(
+    post,
+    path = "/api/v1/profile/email",
+    request_body = ChangeEmailRequest,
+    responses(
+        (status = 202, description = "Confirmation code sent to the new address", body = ChangeEmailResponse),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Unauthorized or wrong password"),
+        (status = 409, description = "Email already in use"),
+        (status = 429, description = "Cooldown or daily limit reached, or too many failed attempts"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Profile",
+    security(("bearer" = []))
+)]
+pub async fn change_email(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(mut payload): Json<ChangeEmailRequest>,
+) -> Result<(StatusCode, Json<ChangeEmailResponse>), AppError> {
+    // Normalize input
+    payload.new_email = normalize_email(&payload.new_email);
+
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Wrong guesses count against the same lockout as login
+    let account_key = format!("login:user:{}", user_id);
+    check_throttle(&state.db, &account_key).await?;
+
+    let user = sqlx::query!(
+        "SELECT email, password_hash, locale FROM users WHERE id = $1",
         user_id
     )
     .fetch_one(&state.db)
     .await?;
 
+    // Re-authenticate before touching the login identity
+    if !verify_password(&payload.password, &user.password_hash).await? {
+        record_login_failure(&state, &account_key, None).await?;
+        return Err(AppError::InvalidCredentials);
+    }
+    clear_throttle(&state.db, &account_key).await?;
+
+    if payload.new_email == user.email {
+        return Err(AppError::ValidationError(
+            "The new email is the same as the current one".to_string(),
+        ));
+    }
+
+    let taken = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM users WHERE email = $1) as "taken!""#,
+        payload.new_email
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    if taken {
+        return Err(AppError::UserAlreadyExists);
+    }
+
+    let locale = state.config.email_locale(user.locale.as_deref());
+    let mut tx = state.db.begin().await?;
+    check_otp_send_limit(
+        &mut tx,
+        user_id,
+        OtpPurpose::EmailChange,
+        state.config.otp_resend_cooldown_secs,
+        state.config.otp_daily_limit,
+    )
+    .await?;
+
+    // A new request replaces any earlier pending address and its codes
+    sqlx::query!(
+        "UPDATE users SET pending_email = $1, updated_at = NOW() WHERE id = $2",
+        payload.new_email,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    invalidate_otps(&mut tx, user_id, OtpPurpose::EmailChange).await?;
+    let code = create_otp(
+        &mut tx,
+        user_id,
+        OtpPurpose::EmailChange,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
+
+    // Code goes to the new address, a heads-up to the current one
+    let email_id = queue_email_change_email(
+        &mut tx,
+        user_id,
+        &payload.new_email,
+        locale,
+        &code,
+        state.config.otp_ttl_minutes,
+    )
+    .await?;
+    queue_email_change_notice(&mut tx, user_id, &user.email, &payload.new_email, locale).await?;
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ChangeEmailResponse {
+            pending_email: payload.new_email,
+            email_id,
+            message: "Check your new email address for a confirmation code.".to_string(),
+        }),
+    ))
+}
+
+/// Confirm the new email address with the emailed code
+#[utoipa::path(
+    post,
+    path = "/api/v1/profile/email/verify",
+    request_body = VerifyEmailChangeRequest,
+    responses(
+        (status = 200, description = "Email changed", body = ProfileResponse),
+        (status = 400, description = "Invalid or expired code, or no change pending"),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Email already in use"),
+        (status = 429, description = "Too many wrong guesses, request a new code"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Profile",
+    security(("bearer" = []))
+)]
+pub async fn verify_email_change(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<VerifyEmailChangeRequest>,
+) -> Result<Json<ProfileResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let pending_email =
+        sqlx::query_scalar!("SELECT pending_email FROM users WHERE id = $1", user_id)
+            .fetch_one(&state.db)
+            .await?;
+
+    if pending_email.is_none() {
+        return Err(AppError::ValidationError(
+            "No email change is pending".to_string(),
+        ));
+    }
+
+    // Check and consume the code
+    consume_otp(
+        &state.db,
+        user_id,
+        &payload.code,
+        OtpPurpose::EmailChange,
+        state.config.otp_max_attempts,
+    )
+    .await?;
+
+    // Swap the address; the unique index rejects it if someone claimed it meanwhile
+    let profile = sqlx::query_as!(
+        UserProfile,
+        r#"
+        UPDATE users
+        SET email = pending_email, pending_email = NULL, updated_at = NOW()
+        WHERE id = $1 AND pending_email IS NOT NULL
+        RETURNING id, username, email, display_name, profile_picture_url, phone_number, locale, pending_email, created_at
+        "#,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| match e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => AppError::UserAlreadyExists,
+        e => AppError::DatabaseError(e),
+    })?
+    .ok_or_else(|| AppError::ValidationError("No email change is pending".to_string()))?;
+
+    tracing::info!("User {} changed their email address", user_id);
+
     Ok(Json(ProfileResponse { profile }))
 }
+
+/// Delete the account
+///
+/// The account is deleted after a grace period (`ACCOUNT_DELETION_GRACE_DAYS`);
+/// logging in before then cancels the deletion. All sessions are signed out
+/// right away. Accounts with 2FA enabled must also send a code. Accounts
+/// created through an identity provider have a random password and must set
+/// one with forgot-password first.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/profile",
+    request_body = DeleteAccountRequest,
+    responses(
+        (status = 202, description = "Deletion scheduled, sessions revoked", body = DeleteAccountResponse),
+        (status = 400, description = "Invalid input, missing or invalid 2FA code"),
+        (status = 401, description = "Unauthorized or wrong password"),
+        (status = 429, description = "Too many failed attempts, see Retry-After"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Profile",
+    security(("bearer" = []))
+)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> Result<(StatusCode, Json<DeleteAccountResponse>), AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Wrong passwords and codes count against the same lockout as login
+    let account_key = format!("login:user:{}", user_id);
+    check_throttle(&state.db, &account_key).await?;
+
+    let user = sqlx::query!(
+        "SELECT email, password_hash, locale, totp_enabled_at FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    // Re-authenticate before an irreversible change
+    if !verify_password(&payload.password, &user.password_hash).await? {
+        record_login_failure(&state, &account_key, None).await?;
+        return Err(AppError::InvalidCredentials);
+    }
+
+    let mut tx = state.db.begin().await?;
+    if user.totp_enabled_at.is_some() {
+        let Some(code) = payload.code.as_deref() else {
+            return Err(AppError::ValidationError(
+                "A two-factor authentication code is required".to_string(),
+            ));
+        };
+        if !check_second_factor(&mut tx, user_id, code).await? {
+            record_login_failure(&state, &account_key, None).await?;
+            return Err(AppError::InvalidOtp);
+        }
+    }
+
+    // Asking again keeps the original date
+    let deletion_scheduled_at = sqlx::query_scalar!(
+        r#"
+        UPDATE users
+        SET deletion_scheduled_at = COALESCE(deletion_scheduled_at, $1), updated_at = NOW()
+        WHERE id = $2
+        RETURNING deletion_scheduled_at as "deletion_scheduled_at!"
+        "#,
+        Utc::now() + Duration::days(state.config.account_deletion_grace_days),
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit_event(
+        &mut tx,
+        user_id,
+        AuditEvent::DeletionRequested,
+        json!({ "deletion_scheduled_at": deletion_scheduled_at }),
+    )
+    .await?;
+
+    let locale = state.config.email_locale(user.locale.as_deref());
+    queue_account_deletion_email(
+        &mut tx,
+        user_id,
+        &user.email,
+        locale,
+        &deletion_scheduled_at.format("%Y-%m-%d").to_string(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    clear_throttle(&state.db, &account_key).await?;
+    revoke_all_sessions(&state, user_id).await?;
+
+    tracing::info!(
+        "User {} scheduled account deletion for {}",
+        user_id,
+        deletion_scheduled_at
+    );
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(DeleteAccountResponse {
+            deletion_scheduled_at,
+            message: "Your account will be deleted. Log in before then to cancel.".to_string(),
+        }),
+    ))
+}
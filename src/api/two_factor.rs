@@ -0,0 +1,319 @@
+use axum::{
+    Extension, Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    api::auth::{record_login_failure, start_session},
+    errors::AppError,
+    middleware::client_ip::ClientIp,
+    models::{
+        app_state::AppState,
+        two_factor::{
+            RecoveryCodesResponse, TwoFactorChallengeResponse, TwoFactorConfirmRequest,
+            TwoFactorDisableRequest, TwoFactorDisableResponse, TwoFactorSetupRequest,
+            TwoFactorSetupResponse, TwoFactorVerifyRequest,
+        },
+        user::LoginResponse,
+    },
+    utils::{
+        password::verify_password,
+        throttle::{check_throttle, clear_throttle},
+        totp::{generate_secret, otpauth_uri, verify_code},
+        two_factor::{
+            check_second_factor, complete_login_challenge, create_login_challenge,
+            login_challenge_user, replace_recovery_codes,
+        },
+    },
+};
+
+/// Start two-factor setup
+///
+/// Generates a new secret; 2FA stays off until a first code is confirmed.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/2fa/setup",
+    request_body = TwoFactorSetupRequest,
+    responses(
+        (status = 200, description = "Secret generated", body = TwoFactorSetupResponse),
+        (status = 400, description = "Invalid input or 2FA already enabled"),
+        (status = 401, description = "Unauthorized or wrong password"),
+        (status = 429, description = "Too many failed attempts, see Retry-After"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication",
+    security(("bearer" = []))
+)]
+pub async fn setup_two_factor(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<TwoFactorSetupRequest>,
+) -> Result<Json<TwoFactorSetupResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Wrong guesses count against the same lockout as login
+    let account_key = format!("login:user:{}", user_id);
+    check_throttle(&state.db, &account_key).await?;
+
+    let user = sqlx::query!(
+        "SELECT username, password_hash, totp_enabled_at FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    if !verify_password(&payload.password, &user.password_hash).await? {
+        record_login_failure(&state, &account_key, None).await?;
+        return Err(AppError::InvalidCredentials);
+    }
+    clear_throttle(&state.db, &account_key).await?;
+
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::ValidationError(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    // Store the secret as pending; a repeated setup replaces it
+    let secret = generate_secret();
+    sqlx::query!(
+        "UPDATE users SET totp_secret = $1, totp_last_step = NULL, updated_at = NOW() WHERE id = $2",
+        secret,
+        user_id
+    )
+    .execute(&state.db)
+    .await?;
+
+    Ok(Json(TwoFactorSetupResponse {
+        otpauth_uri: otpauth_uri(&state.config.totp_issuer, &user.username, &secret),
+        secret,
+        message: "Add the secret to your authenticator app, then confirm with a code.".to_string(),
+    }))
+}
+
+/// Confirm two-factor setup with a first code
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/2fa/confirm",
+    request_body = TwoFactorConfirmRequest,
+    responses(
+        (status = 200, description = "2FA enabled; recovery codes are shown only this once", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code, no setup started or 2FA already enabled"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication",
+    security(("bearer" = []))
+)]
+pub async fn confirm_two_factor(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<TwoFactorConfirmRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let mut tx = state.db.begin().await?;
+    let user = sqlx::query!(
+        "SELECT totp_secret, totp_enabled_at, totp_last_step FROM users WHERE id = $1 FOR UPDATE",
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::ValidationError(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    let secret = user
+        .totp_secret
+        .ok_or_else(|| AppError::ValidationError("Start two-factor setup first".to_string()))?;
+    let step =
+        verify_code(&secret, &payload.code, user.totp_last_step).ok_or(AppError::InvalidOtp)?;
+
+    // Enable 2FA and issue the recovery codes together
+    sqlx::query!(
+        "UPDATE users SET totp_enabled_at = NOW(), totp_last_step = $1, updated_at = NOW() WHERE id = $2",
+        step,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let recovery_codes = replace_recovery_codes(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    tracing::info!("User {} enabled two-factor authentication", user_id);
+
+    Ok(Json(RecoveryCodesResponse {
+        recovery_codes,
+        message: "Two-factor authentication enabled. Store these recovery codes somewhere safe."
+            .to_string(),
+    }))
+}
+
+/// Turn off two-factor authentication
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/2fa/disable",
+    request_body = TwoFactorDisableRequest,
+    responses(
+        (status = 200, description = "2FA disabled", body = TwoFactorDisableResponse),
+        (status = 400, description = "Invalid input, invalid code or 2FA not enabled"),
+        (status = 401, description = "Unauthorized or wrong password"),
+        (status = 429, description = "Too many failed attempts, see Retry-After"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication",
+    security(("bearer" = []))
+)]
+pub async fn disable_two_factor(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(payload): Json<TwoFactorDisableRequest>,
+) -> Result<Json<TwoFactorDisableResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Wrong passwords and codes count against the same lockout as login
+    let account_key = format!("login:user:{}", user_id);
+    check_throttle(&state.db, &account_key).await?;
+
+    let user = sqlx::query!(
+        "SELECT password_hash, totp_enabled_at FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    if !verify_password(&payload.password, &user.password_hash).await? {
+        record_login_failure(&state, &account_key, None).await?;
+        return Err(AppError::InvalidCredentials);
+    }
+
+    if user.totp_enabled_at.is_none() {
+        return Err(AppError::ValidationError(
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+
+    // Require the second factor too, so a leaked password alone cannot turn it off
+    let mut tx = state.db.begin().await?;
+    if !check_second_factor(&mut tx, user_id, &payload.code).await? {
+        record_login_failure(&state, &account_key, None).await?;
+        return Err(AppError::InvalidOtp);
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM totp_recovery_codes WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!("DELETE FROM login_challenges WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    clear_throttle(&state.db, &account_key).await?;
+    tracing::info!("User {} disabled two-factor authentication", user_id);
+
+    Ok(Json(TwoFactorDisableResponse {
+        message: "Two-factor authentication disabled".to_string(),
+    }))
+}
+
+/// Complete a login with a 2FA code
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/2fa/verify",
+    request_body = TwoFactorVerifyRequest,
+    responses(
+        (status = 200, description = "Login successful", body = LoginResponse),
+        (status = 400, description = "Invalid code"),
+        (status = 401, description = "Invalid or expired challenge token"),
+        (status = 429, description = "Too many wrong codes, log in again or see Retry-After"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication"
+)]
+pub async fn verify_two_factor(
+    State(state): State<AppState>,
+    ClientIp(client_ip): ClientIp,
+    Json(payload): Json<TwoFactorVerifyRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    // Validate input
+    payload
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let user_id = login_challenge_user(&state.db, &payload.challenge_token).await?;
+
+    // Wrong codes count against the login lockout, so new challenges do not
+    // bring fresh guesses
+    let account_key = format!("login:user:{}", user_id);
+    let ip_key = client_ip.map(|ip| format!("login:ip:{}", ip));
+
+    check_throttle(&state.db, &account_key).await?;
+    if let Some(ip_key) = &ip_key {
+        check_throttle(&state.db, ip_key).await?;
+    }
+
+    let accepted = complete_login_challenge(
+        &state.db,
+        &payload.challenge_token,
+        &payload.code,
+        state.config.otp_max_attempts,
+    )
+    .await?;
+
+    if !accepted {
+        record_login_failure(&state, &account_key, ip_key.as_deref()).await?;
+        return Err(AppError::InvalidOtp);
+    }
+
+    clear_throttle(&state.db, &account_key).await?;
+    Ok(Json(start_session(&state, user_id).await?))
+}
+
+/// Answer a successful first login step with a challenge for the 2FA code
+pub async fn two_factor_challenge(state: &AppState, user_id: Uuid) -> Result<Response, AppError> {
+    let expires_in = state.config.two_factor_challenge_ttl_secs;
+    let mut conn = state.db.acquire().await?;
+    let challenge_token = create_login_challenge(&mut conn, user_id, expires_in).await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(TwoFactorChallengeResponse {
+            challenge_token,
+            expires_in,
+            message: "Enter the code from your authenticator app or a recovery code.".to_string(),
+        }),
+    )
+        .into_response())
+}
@@ -0,0 +1,407 @@
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, State},
+    http::HeaderValue,
+    middleware as axum_middleware,
+    routing::get,
+};
+
+use serde_json::{Value, json};
+use sqlx::postgres::PgPoolOptions;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::api::auth::{
+    change_password, forgot_password, jwks, login, logout, logout_all, refresh, register,
+    request_login_code, resend_otp, reset_password, verify_login_code, verify_otp,
+};
+use crate::api::data_export::{download_data_export, get_data_export, request_data_export};
+use crate::api::emails::get_email_status;
+use crate::api::joint_members::{
+    ban_member, demote_moderator, kick_member, promote_moderator, transfer_ownership, unban_member,
+};
+use crate::api::joints::{
+    close_joint, create_joint, delete_joint, get_active_joints, get_joint, join_joint,
+    leave_joint, list_nearby_joints, update_joint,
+};
+use crate::api::oidc::{complete_oidc_login, list_oidc_providers, start_oidc_login};
+use crate::api::profile::{
+    change_email, delete_account, get_profile, update_profile, verify_email_change,
+};
+use crate::api::two_factor::{
+    confirm_two_factor, disable_two_factor, setup_two_factor, verify_two_factor,
+};
+use crate::config::AppConfig;
+use crate::jobs::spawn_background_jobs;
+use crate::mailer;
+use crate::middleware::auth::auth_middleware;
+use crate::middleware::ws_auth::ws_auth_middleware;
+use crate::models::app_state::AppState;
+use crate::utils::migrations;
+use crate::ws::handler::websocket_handler;
+
+/// API Documentation
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::api::auth::register,
+        crate::api::auth::verify_otp,
+        crate::api::auth::resend_otp,
+        crate::api::auth::login,
+        crate::api::auth::request_login_code,
+        crate::api::auth::verify_login_code,
+        crate::api::auth::refresh,
+        crate::api::auth::forgot_password,
+        crate::api::auth::reset_password,
+        crate::api::auth::logout,
+        crate::api::auth::logout_all,
+        crate::api::auth::change_password,
+        crate::api::auth::jwks,
+        crate::api::oidc::list_oidc_providers,
+        crate::api::oidc::start_oidc_login,
+        crate::api::oidc::complete_oidc_login,
+        crate::api::two_factor::setup_two_factor,
+        crate::api::two_factor::confirm_two_factor,
+        crate::api::two_factor::disable_two_factor,
+        crate::api::two_factor::verify_two_factor,
+        crate::api::emails::get_email_status,
+        crate::api::joints::create_joint,           
+        crate::api::joints::list_nearby_joints,   
+        crate::api::joints::join_joint, 
+        crate::api::joints::get_active_joints,      
+        crate::api::joints::leave_joint, 
+        crate::api::joints::get_joint,
+        crate::api::joints::update_joint,
+        crate::api::joints::close_joint,
+        crate::api::joints::delete_joint,
+        crate::api::joint_members::transfer_ownership,
+        crate::api::joint_members::promote_moderator,
+        crate::api::joint_members::demote_moderator,
+        crate::api::joint_members::kick_member,
+        crate::api::joint_members::ban_member,
+        crate::api::joint_members::unban_member,
+        crate::api::profile::get_profile,
+        crate::api::profile::update_profile,
+        crate::api::profile::change_email,
+        crate::api::profile::verify_email_change,         
+        crate::api::profile::delete_account,
+        crate::api::data_export::request_data_export,
+        crate::api::data_export::get_data_export,
+        crate::api::data_export::download_data_export,
+    ),
+    components(
+        schemas(
+            crate::models::user::RegisterRequest,
+            crate::models::user::RegisterResponse,
+            crate::models::user::VerifyOtpRequest,
+            crate::models::user::VerifyOtpResponse,
+            crate::models::user::ResendOtpRequest,
+            crate::models::user::ResendOtpResponse,
+            crate::models::user::LoginRequest,
+            crate::models::user::LoginResponse,
+            crate::models::user::LoginCodeRequest,
+            crate::models::user::LoginCodeResponse,
+            crate::models::user::VerifyLoginCodeRequest,
+            crate::models::user::RefreshTokenRequest,
+            crate::models::user::ForgotPasswordRequest,
+            crate::models::user::ForgotPasswordResponse,
+            crate::models::user::ResetPasswordRequest,
+            crate::models::user::ResetPasswordResponse,
+            crate::models::user::LogoutRequest,
+            crate::models::user::ChangePasswordRequest,
+            crate::models::user::LogoutResponse,
+            crate::models::oidc::OidcProvidersResponse,
+            crate::models::oidc::OidcAuthorizeResponse,
+            crate::models::oidc::OidcCallbackRequest,
+            crate::models::two_factor::TwoFactorSetupRequest,
+            crate::models::two_factor::TwoFactorSetupResponse,
+            crate::models::two_factor::TwoFactorConfirmRequest,
+            crate::models::two_factor::RecoveryCodesResponse,
+            crate::models::two_factor::TwoFactorDisableRequest,
+            crate::models::two_factor::TwoFactorDisableResponse,
+            crate::models::two_factor::TwoFactorChallengeResponse,
+            crate::models::two_factor::TwoFactorVerifyRequest,
+            crate::models::email::EmailStatusResponse,
+            crate::models::joint::CreateJointRequest,      
+            crate::models::joint::CreateJointResponse,    
+            crate::models::joint::ListJointsRequest,     
+            crate::models::joint::ListJointsResponse,   
+            crate::models::joint::JoinJointRequest,    
+            crate::models::joint::JoinJointResponse,  
+            crate::models::joint::Joint,             
+            crate::models::joint::JointWithDistance,
+            crate::models::joint::JointDetailResponse,
+            crate::models::joint::UpdateJointRequest,
+            crate::models::joint::JointActionResponse,
+            crate::models::joint::JointRole,
+            crate::models::joint::JointMemberRequest,
+            crate::models::joint::RoleChangeResponse,
+            crate::models::joint::BanMemberRequest,
+            crate::models::joint::BanResponse,
+            crate::models::profile::UserProfile,         
+            crate::models::profile::UpdateProfileRequest, 
+            crate::models::profile::ProfileResponse,
+            crate::models::profile::ChangeEmailRequest,
+            crate::models::profile::ChangeEmailResponse,
+            crate::models::profile::VerifyEmailChangeRequest,
+            crate::models::profile::DeleteAccountRequest,
+            crate::models::profile::DeleteAccountResponse,
+            crate::models::data_export::DataExportRequestedResponse,
+            crate::models::data_export::DataExportStatusResponse,
+        )
+    ),
+    tags(
+        (name = "Authentication", description = "User authentication endpoints"),
+        (name = "Emails", description = "Transactional email delivery status"),
+        (name = "Joints", description = "Location-based group endpoints"),
+        (name = "Profile", description = "User profile management")
+    ),
+    info(
+        title = "Spot Feed API",
+        version = "0.1.0",
+        description = "Location-based social networking API",
+        contact(
+            name = "Spot Feed Team",
+            email = "support@spotfeed.com"
+        )
+    )
+)]
+pub struct ApiDoc;
+
+async fn hello_world() -> &'static str {
+    "Hello from Spot Feed! 🚀"
+}
+
+async fn health_check(State(state): State<AppState>) -> Json<Value> {
+    // Test database connection and report the schema version
+    match migrations::schema_version(&state.db).await {
+        Ok(schema_version) => Json(json!({
+            "status": "healthy",
+            "database": "connected",
+            "schema_version": schema_version,
+            "expected_schema_version": migrations::latest_known_version()
+        })),
+        Err(e) => Json(json!({
+            "status": "unhealthy",
+            "database": "disconnected",
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// Connect to the database, migrate it and start background jobs.
+///
+/// Panics when the database is unreachable or its schema cannot be used,
+/// since the server cannot do anything useful in that case.
+pub async fn init_state(database_url: &str, config: AppConfig) -> AppState {
+    let db = PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .connect(database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    // Bring the schema up to date, or at least make sure this build understands it
+    if config.run_migrations {
+        migrations::run_migrations(&db)
+            .await
+            .expect("Failed to migrate database");
+    } else {
+        tracing::info!("RUN_MIGRATIONS is disabled, skipping database migrations");
+        migrations::ensure_schema_compatible(&db)
+            .await
+            .expect("Incompatible database schema");
+    }
+
+    let mailer = mailer::from_config(&config).expect("Invalid email transport configuration");
+
+    let state = AppState::new(db, config, mailer);
+
+    // Deliver queued email in the background
+    spawn_background_jobs(state.clone());
+
+    state
+}
+
+/// Build the application router with every route, the API docs and global layers
+pub fn build_app(state: AppState) -> Router {
+    // Protected routes that require authentication
+    let protected_routes = Router::new()
+        .route("/api/v1/auth/logout", axum::routing::post(logout))
+        .route("/api/v1/auth/logout-all", axum::routing::post(logout_all))
+        .route(
+            "/api/v1/auth/change-password",
+            axum::routing::post(change_password),
+        )
+        .route(
+            "/api/v1/auth/2fa/setup",
+            axum::routing::post(setup_two_factor),
+        )
+        .route(
+            "/api/v1/auth/2fa/confirm",
+            axum::routing::post(confirm_two_factor),
+        )
+        .route(
+            "/api/v1/auth/2fa/disable",
+            axum::routing::post(disable_two_factor),
+        )
+        .route("/api/v1/joints", axum::routing::post(create_joint))
+        .route(
+            "/api/v1/joints/active",
+            axum::routing::get(get_active_joints),
+        ) // ADD
+        .route("/api/v1/joints/join", axum::routing::post(join_joint))
+        .route("/api/v1/joints/leave", axum::routing::post(leave_joint))
+        .route(
+            "/api/v1/joints/{id}",
+            axum::routing::get(get_joint)
+                .patch(update_joint)
+                .delete(delete_joint),
+        )
+        .route("/api/v1/joints/{id}/close", axum::routing::post(close_joint))
+        .route(
+            "/api/v1/joints/{id}/transfer",
+            axum::routing::post(transfer_ownership),
+        )
+        .route(
+            "/api/v1/joints/{id}/moderators",
+            axum::routing::post(promote_moderator),
+        )
+        .route(
+            "/api/v1/joints/{id}/moderators/{user_id}",
+            axum::routing::delete(demote_moderator),
+        )
+        .route("/api/v1/joints/{id}/kick", axum::routing::post(kick_member))
+        .route("/api/v1/joints/{id}/bans", axum::routing::post(ban_member))
+        .route(
+            "/api/v1/joints/{id}/bans/{user_id}",
+            axum::routing::delete(unban_member),
+        )
+        .route("/api/v1/profile", axum::routing::get(get_profile))
+        .route("/api/v1/profile", axum::routing::put(update_profile))
+        .route("/api/v1/profile", axum::routing::delete(delete_account))
+        .route("/api/v1/profile/email", axum::routing::post(change_email))
+        .route(
+            "/api/v1/profile/email/verify",
+            axum::routing::post(verify_email_change),
+        )
+        .route(
+            "/api/v1/profile/exports",
+            axum::routing::post(request_data_export),
+        )
+        .route(
+            "/api/v1/profile/exports/{id}",
+            axum::routing::get(get_data_export),
+        )
+        .route_layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    // WebSocket routes (separate auth middleware)
+    let ws_routes = Router::new()
+        .route(
+            "/ws/joint/{joint_id}",
+            axum::routing::get(websocket_handler),
+        )
+        .route_layer(axum_middleware::from_fn_with_state(
+            state.clone(),
+            ws_auth_middleware,
+        ));
+
+    // Public routes
+    let public_routes = Router::new()
+        .route("/", get(hello_world))
+        .route("/api/health", get(health_check))
+        .route("/.well-known/jwks.json", get(jwks))
+        // Authentication routes
+        .route("/api/v1/auth/register", axum::routing::post(register))
+        .route("/api/v1/auth/verify-otp", axum::routing::post(verify_otp))
+        .route("/api/v1/auth/resend-otp", axum::routing::post(resend_otp))
+        .route("/api/v1/auth/login", axum::routing::post(login))
+        .route(
+            "/api/v1/auth/login-code",
+            axum::routing::post(request_login_code),
+        )
+        .route(
+            "/api/v1/auth/login-code/verify",
+            axum::routing::post(verify_login_code),
+        )
+        .route("/api/v1/auth/refresh", axum::routing::post(refresh))
+        .route(
+            "/api/v1/auth/2fa/verify",
+            axum::routing::post(verify_two_factor),
+        )
+        .route(
+            "/api/v1/auth/oidc/providers",
+            axum::routing::get(list_oidc_providers),
+        )
+        .route(
+            "/api/v1/auth/oidc/{provider}/authorize",
+            axum::routing::post(start_oidc_login),
+        )
+        .route(
+            "/api/v1/auth/oidc/{provider}/callback",
+            axum::routing::post(complete_oidc_login),
+        )
+        .route(
+            "/api/v1/auth/forgot-password",
+            axum::routing::post(forgot_password),
+        )
+        .route(
+            "/api/v1/auth/reset-password",
+            axum::routing::post(reset_password),
+        )
+        // Email delivery status
+        .route("/api/v1/emails/{id}", axum::routing::get(get_email_status))
+        // Data export download, authorized by its token
+        .route(
+            "/api/v1/exports/download",
+            axum::routing::get(download_data_export),
+        )
+        // Public joints routes
+        .route(
+            "/api/v1/joints/nearby",
+            axum::routing::post(list_nearby_joints),
+        );
+
+    let router = Router::new()
+        .merge(ws_routes)
+        .merge(protected_routes)
+        .merge(public_routes)
+        // Swagger UI
+        .merge(SwaggerUi::new("/api/docs").url("/api/openapi.json", ApiDoc::openapi()))
+        .layer(DefaultBodyLimit::max(state.config.max_request_body_bytes));
+
+    let router = match cors_layer(&state.config) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+
+    router.with_state(state)
+}
+
+/// Build the CORS layer from the configured origins (None when CORS is disabled)
+fn cors_layer(config: &AppConfig) -> Option<CorsLayer> {
+    if config.cors_allowed_origins.is_empty() {
+        return None;
+    }
+
+    let origins = if config.cors_allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        let origins: Vec<HeaderValue> = config
+            .cors_allowed_origins
+            .iter()
+            .filter_map(|origin| origin.parse().ok())
+            .collect();
+        AllowOrigin::list(origins)
+    };
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(Any)
+            .allow_headers(Any),
+    )
+}
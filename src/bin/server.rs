@@ -0,0 +1,87 @@
+//! Standalone server for running Spot Feed without the Shuttle runtime.
+//!
+//! Reads its configuration from environment variables, plus `DATABASE_URL`,
+//! `BIND_ADDRESS` and `SHUTDOWN_GRACE_SECS`.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tracing_subscriber::EnvFilter;
+
+use spot_feed::app::{build_app, init_state};
+use spot_feed::config::AppConfig;
+use spot_feed::ws::manager::ConnectionManager;
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
+    let config = AppConfig::from_env().expect("Invalid configuration");
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let bind_address = std::env::var("BIND_ADDRESS").unwrap_or_else(|_| "0.0.0.0:8000".to_string());
+    let grace_period = Duration::from_secs(
+        std::env::var("SHUTDOWN_GRACE_SECS")
+            .ok()
+            .map(|secs| secs.parse().expect("SHUTDOWN_GRACE_SECS must be a number"))
+            .unwrap_or(10),
+    );
+
+    let state = init_state(&database_url, config).await;
+    let ws_manager = state.ws_manager.clone();
+    let app = build_app(state);
+
+    let listener = tokio::net::TcpListener::bind(&bind_address)
+        .await
+        .expect("Failed to bind address");
+    tracing::info!("Listening on {}", bind_address);
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(ws_manager.clone()))
+    .await
+    .expect("Server error");
+
+    // HTTP requests are done; give WebSocket tasks time to send their close frames
+    let remaining = ws_manager.wait_for_drain(grace_period).await;
+    if remaining > 0 {
+        tracing::warn!(
+            "Shutting down with {} WebSocket connections still open",
+            remaining
+        );
+    }
+    tracing::info!("Server stopped");
+}
+
+/// Resolve on Ctrl+C or SIGTERM, closing all WebSocket connections
+async fn shutdown_signal(ws_manager: ConnectionManager) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutdown signal received, closing WebSocket connections");
+    ws_manager.close_all().await;
+}
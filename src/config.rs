@@ -0,0 +1,544 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use argon2::Params;
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use url::Url;
+
+use crate::mailer::templates;
+use crate::utils::jwt::JwtKeys;
+use crate::utils::password_policy::PasswordPolicy;
+
+/// Placeholder secret that used to be hard-coded; refuse to start with it
+const INSECURE_JWT_SECRET: &str = "your-super-secret-jwt-key-change-in-production";
+
+/// Minimum length (in bytes) for the HS256 signing secret
+const MIN_JWT_SECRET_LENGTH: usize = 32;
+
+/// Configuration error raised while loading `AppConfig`
+#[derive(Debug)]
+pub enum ConfigError {
+    Missing(&'static str),
+    Invalid { key: &'static str, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Missing(key) => write!(f, "{} must be set", key),
+            ConfigError::Invalid { key, reason } => write!(f, "{} is invalid: {}", key, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// How outgoing email is delivered (`EMAIL_TRANSPORT`)
+#[derive(Clone)]
+pub enum EmailTransport {
+    /// Resend API (`resend`, default)
+    Resend { api_key: String },
+    /// Plain SMTP server (`smtp`)
+    Smtp(SmtpConfig),
+    /// Write messages as JSON files into a directory (`file`)
+    File { dir: PathBuf },
+    /// Keep messages in memory (`memory`, tests only)
+    Memory,
+}
+
+/// SMTP connection settings
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: SmtpTls,
+}
+
+/// SMTP transport security (`SMTP_TLS`)
+#[derive(Debug, Clone, Copy)]
+pub enum SmtpTls {
+    /// Upgrade a plain connection with STARTTLS (`starttls`, default)
+    StartTls,
+    /// Implicit TLS (`tls`)
+    Tls,
+    /// Unencrypted, for local mail catchers only (`none`)
+    None,
+}
+
+impl FromStr for SmtpTls {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "starttls" => Ok(SmtpTls::StartTls),
+            "tls" => Ok(SmtpTls::Tls),
+            "none" => Ok(SmtpTls::None),
+            other => Err(format!("unknown SMTP TLS mode '{}'", other)),
+        }
+    }
+}
+
+/// External OpenID Connect identity provider, one entry of `OIDC_PROVIDERS`
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcProviderConfig {
+    /// Short name used in URLs and stored with linked identities, e.g. "google"
+    pub name: String,
+    /// Issuer URL; the discovery document is read from `<issuer>/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    /// Omitted for public clients, which rely on PKCE alone
+    pub client_secret: Option<String>,
+    /// Where the provider sends the user back with the authorization code
+    pub redirect_uri: String,
+    /// Space-separated scopes to request
+    #[serde(default = "default_oidc_scopes")]
+    pub scopes: String,
+}
+
+fn default_oidc_scopes() -> String {
+    "openid email profile".to_string()
+}
+
+/// Application configuration, loaded once at startup
+#[derive(Clone)]
+pub struct AppConfig {
+    /// Keys used to sign and verify JWT access tokens
+    pub jwt_keys: JwtKeys,
+    /// Access token lifetime in seconds
+    pub access_token_ttl_secs: i64,
+    /// Refresh token lifetime in days
+    pub refresh_token_ttl_days: i64,
+    /// How long a "not revoked" answer for an access token is cached, in seconds
+    pub revocation_cache_ttl_secs: u64,
+    /// OTP code lifetime in minutes
+    pub otp_ttl_minutes: i64,
+    /// Minimum delay between two OTP emails to the same user, in seconds
+    pub otp_resend_cooldown_secs: i64,
+    /// Maximum number of OTP emails per user and purpose in 24 hours
+    pub otp_daily_limit: i64,
+    /// Wrong guesses allowed before an OTP code is locked
+    pub otp_max_attempts: i32,
+    /// Issuer name shown in authenticator apps
+    pub totp_issuer: String,
+    /// Lifetime of the challenge token between password and 2FA code, in seconds
+    pub two_factor_challenge_ttl_secs: i64,
+    /// External identity providers for "Sign in with ..." (empty disables OIDC sign-in)
+    pub oidc_providers: Vec<OidcProviderConfig>,
+    /// Time to complete a sign-in at the identity provider, in seconds
+    pub oidc_state_ttl_secs: i64,
+    /// Days between a deletion request and the actual removal of the account
+    pub account_deletion_grace_days: i64,
+    /// How long a finished personal data export can be downloaded, in hours
+    pub data_export_ttl_hours: i64,
+    /// Failed logins per account before it is temporarily locked
+    pub login_max_failures: i32,
+    /// Failed logins per client IP before it is temporarily locked
+    pub login_ip_max_failures: i32,
+    /// First lockout duration in seconds, doubled on every further failure
+    pub login_lockout_base_secs: i64,
+    /// Upper bound for the lockout duration in seconds
+    pub login_lockout_max_secs: i64,
+    /// Rules for new passwords (register, reset, change)
+    pub password_policy: PasswordPolicy,
+    /// Argon2id cost parameters for new password hashes; weaker hashes are upgraded on login
+    pub argon2_params: Params,
+    /// Maximum number of pooled database connections
+    pub db_max_connections: u32,
+    /// Apply pending database migrations at startup
+    pub run_migrations: bool,
+    /// Email delivery backend
+    pub email_transport: EmailTransport,
+    /// Sender address for outgoing mail, e.g. "Spot Feed <noreply@spotfeed.com>"
+    pub email_from: String,
+    /// Email language for users without a (supported) locale of their own
+    pub email_default_locale: String,
+    /// Delivery attempts before a queued email is dead-lettered
+    pub email_max_attempts: i32,
+    /// First retry delay for a failed email in seconds, doubled on every further failure
+    pub email_retry_base_secs: i64,
+    /// Upper bound for the email retry delay in seconds
+    pub email_retry_max_secs: i64,
+    /// How often the outbox worker polls for due email, in seconds
+    pub email_worker_interval_secs: u64,
+    /// Origins allowed by CORS ("*" allows any origin, empty disables CORS)
+    pub cors_allowed_origins: Vec<String>,
+    /// Reverse proxies in front of the server that append to X-Forwarded-For
+    /// (0 uses the socket peer address as the client IP)
+    pub trusted_proxy_hops: usize,
+    /// Maximum accepted request body size in bytes
+    pub max_request_body_bytes: usize,
+    /// Maximum length of a chat message in characters
+    pub max_message_length: usize,
+    /// Maximum number of concurrent WebSocket connections per joint
+    pub max_joint_connections: usize,
+}
+
+impl AppConfig {
+    /// Load configuration from the Shuttle secret store, falling back to environment variables
+    pub fn from_secrets(secrets: &shuttle_runtime::SecretStore) -> Result<Self, ConfigError> {
+        Self::load(|key| {
+            secrets
+                .get(key)
+                .or_else(|| std::env::var(key).ok())
+                // Shuttle serves through its proxy and passes no peer address
+                .or_else(|| (key == "TRUSTED_PROXY_HOPS").then(|| "1".to_string()))
+        })
+    }
+
+    /// Load configuration from environment variables only
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::load(|key| std::env::var(key).ok())
+    }
+
+    /// Build and validate the configuration from a key lookup function
+    pub fn load(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let source = Source { lookup: &lookup };
+
+        let config = Self {
+            jwt_keys: jwt_keys(&source)?,
+            access_token_ttl_secs: source.parse_or("ACCESS_TOKEN_TTL_SECS", 15 * 60)?,
+            refresh_token_ttl_days: source.parse_or("REFRESH_TOKEN_TTL_DAYS", 30)?,
+            revocation_cache_ttl_secs: source.parse_or("REVOCATION_CACHE_TTL_SECS", 30)?,
+            otp_ttl_minutes: source.parse_or("OTP_TTL_MINUTES", 10)?,
+            otp_resend_cooldown_secs: source.parse_or("OTP_RESEND_COOLDOWN_SECS", 60)?,
+            otp_daily_limit: source.parse_or("OTP_DAILY_LIMIT", 5)?,
+            otp_max_attempts: source.parse_or("OTP_MAX_ATTEMPTS", 5)?,
+            totp_issuer: source
+                .optional("TOTP_ISSUER")
+                .unwrap_or_else(|| "Spot Feed".to_string()),
+            two_factor_challenge_ttl_secs: source.parse_or("TWO_FACTOR_CHALLENGE_TTL_SECS", 300)?,
+            oidc_providers: oidc_providers(&source)?,
+            oidc_state_ttl_secs: source.parse_or("OIDC_STATE_TTL_SECS", 600)?,
+            account_deletion_grace_days: source.parse_or("ACCOUNT_DELETION_GRACE_DAYS", 30)?,
+            data_export_ttl_hours: source.parse_or("DATA_EXPORT_TTL_HOURS", 48)?,
+            login_max_failures: source.parse_or("LOGIN_MAX_FAILURES", 5)?,
+            login_ip_max_failures: source.parse_or("LOGIN_IP_MAX_FAILURES", 20)?,
+            login_lockout_base_secs: source.parse_or("LOGIN_LOCKOUT_BASE_SECS", 30)?,
+            login_lockout_max_secs: source.parse_or("LOGIN_LOCKOUT_MAX_SECS", 3600)?,
+            password_policy: PasswordPolicy {
+                min_length: source.parse_or("PASSWORD_MIN_LENGTH", 8)?,
+                max_length: source.parse_or("PASSWORD_MAX_LENGTH", 128)?,
+                require_lowercase: source.parse_or("PASSWORD_REQUIRE_LOWERCASE", false)?,
+                require_uppercase: source.parse_or("PASSWORD_REQUIRE_UPPERCASE", false)?,
+                require_digit: source.parse_or("PASSWORD_REQUIRE_DIGIT", false)?,
+                require_symbol: source.parse_or("PASSWORD_REQUIRE_SYMBOL", false)?,
+                reject_common: source.parse_or("PASSWORD_REJECT_COMMON", true)?,
+            },
+            argon2_params: argon2_params(&source)?,
+            db_max_connections: source.parse_or("DATABASE_MAX_CONNECTIONS", 5)?,
+            run_migrations: source.parse_or("RUN_MIGRATIONS", true)?,
+            email_transport: email_transport(&source)?,
+            email_from: source
+                .optional("EMAIL_FROM")
+                .unwrap_or_else(|| "Spot Feed <onboarding@resend.dev>".to_string()),
+            email_default_locale: source
+                .optional("EMAIL_DEFAULT_LOCALE")
+                .unwrap_or_else(|| templates::BASE_LOCALE.to_string()),
+            email_max_attempts: source.parse_or("EMAIL_MAX_ATTEMPTS", 8)?,
+            email_retry_base_secs: source.parse_or("EMAIL_RETRY_BASE_SECS", 30)?,
+            email_retry_max_secs: source.parse_or("EMAIL_RETRY_MAX_SECS", 3600)?,
+            email_worker_interval_secs: source.parse_or("EMAIL_WORKER_INTERVAL_SECS", 5)?,
+            cors_allowed_origins: source
+                .optional("CORS_ALLOWED_ORIGINS")
+                .map(|origins| {
+                    origins
+                        .split(',')
+                        .map(|origin| origin.trim().to_string())
+                        .filter(|origin| !origin.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            trusted_proxy_hops: source.parse_or("TRUSTED_PROXY_HOPS", 0)?,
+            max_request_body_bytes: source.parse_or("MAX_REQUEST_BODY_BYTES", 64 * 1024)?,
+            max_message_length: source.parse_or("MAX_MESSAGE_LENGTH", 2000)?,
+            max_joint_connections: source.parse_or("MAX_JOINT_CONNECTIONS", 500)?,
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Sanity-check loaded values so misconfiguration fails at startup
+    fn validate(&self) -> Result<(), ConfigError> {
+        ensure_positive("ACCESS_TOKEN_TTL_SECS", self.access_token_ttl_secs)?;
+        ensure_positive("REFRESH_TOKEN_TTL_DAYS", self.refresh_token_ttl_days)?;
+        ensure_positive("OTP_TTL_MINUTES", self.otp_ttl_minutes)?;
+        ensure_positive("OTP_RESEND_COOLDOWN_SECS", self.otp_resend_cooldown_secs)?;
+        ensure_positive("OTP_DAILY_LIMIT", self.otp_daily_limit)?;
+        ensure_positive("OTP_MAX_ATTEMPTS", self.otp_max_attempts)?;
+        ensure_positive(
+            "TWO_FACTOR_CHALLENGE_TTL_SECS",
+            self.two_factor_challenge_ttl_secs,
+        )?;
+        ensure_positive("OIDC_STATE_TTL_SECS", self.oidc_state_ttl_secs)?;
+        ensure_positive(
+            "ACCOUNT_DELETION_GRACE_DAYS",
+            self.account_deletion_grace_days,
+        )?;
+        ensure_positive("DATA_EXPORT_TTL_HOURS", self.data_export_ttl_hours)?;
+        ensure_positive("LOGIN_MAX_FAILURES", self.login_max_failures)?;
+        ensure_positive("LOGIN_IP_MAX_FAILURES", self.login_ip_max_failures)?;
+        ensure_positive("LOGIN_LOCKOUT_BASE_SECS", self.login_lockout_base_secs)?;
+        ensure_positive("LOGIN_LOCKOUT_MAX_SECS", self.login_lockout_max_secs)?;
+        ensure_positive("DATABASE_MAX_CONNECTIONS", self.db_max_connections)?;
+        ensure_positive("MAX_REQUEST_BODY_BYTES", self.max_request_body_bytes)?;
+        ensure_positive("MAX_MESSAGE_LENGTH", self.max_message_length)?;
+        ensure_positive("MAX_JOINT_CONNECTIONS", self.max_joint_connections)?;
+        ensure_positive("EMAIL_MAX_ATTEMPTS", self.email_max_attempts)?;
+        ensure_positive("EMAIL_RETRY_BASE_SECS", self.email_retry_base_secs)?;
+        ensure_positive("EMAIL_RETRY_MAX_SECS", self.email_retry_max_secs)?;
+        ensure_positive(
+            "EMAIL_WORKER_INTERVAL_SECS",
+            self.email_worker_interval_secs,
+        )?;
+
+        ensure_positive("PASSWORD_MIN_LENGTH", self.password_policy.min_length)?;
+
+        if self.password_policy.max_length < self.password_policy.min_length {
+            return Err(ConfigError::Invalid {
+                key: "PASSWORD_MAX_LENGTH",
+                reason: "must not be less than PASSWORD_MIN_LENGTH".to_string(),
+            });
+        }
+
+        if self.email_from.trim().is_empty() {
+            return Err(ConfigError::Invalid {
+                key: "EMAIL_FROM",
+                reason: "must not be empty".to_string(),
+            });
+        }
+
+        if !templates::is_supported_locale(&self.email_default_locale) {
+            return Err(ConfigError::Invalid {
+                key: "EMAIL_DEFAULT_LOCALE",
+                reason: format!("no email templates for '{}'", self.email_default_locale),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Locale to render a user's email in, given their stored preference
+    pub fn email_locale(&self, user_locale: Option<&str>) -> &'static str {
+        templates::resolve_locale(user_locale, &self.email_default_locale)
+    }
+}
+
+/// Read the access token signing keys selected by `JWT_ALGORITHM`
+fn jwt_keys(source: &Source) -> Result<JwtKeys, ConfigError> {
+    let algorithm = source
+        .optional("JWT_ALGORITHM")
+        .unwrap_or_else(|| "HS256".to_string());
+
+    let algorithm = match algorithm.trim().to_ascii_uppercase().as_str() {
+        "HS256" => return hmac_keys(source),
+        "RS256" => Algorithm::RS256,
+        "EDDSA" => Algorithm::EdDSA,
+        other => {
+            return Err(ConfigError::Invalid {
+                key: "JWT_ALGORITHM",
+                reason: format!("unsupported algorithm '{}'", other),
+            });
+        }
+    };
+
+    // Secret stores often hold PEM keys on a single line with escaped newlines
+    let private_key = source.required("JWT_PRIVATE_KEY")?.replace("\\n", "\n");
+
+    JwtKeys::from_pem(
+        algorithm,
+        &private_key,
+        source.optional("JWT_KEY_ID"),
+        source.optional("JWT_PREVIOUS_KEYS").as_deref(),
+    )
+    .map_err(|reason| ConfigError::Invalid {
+        key: "JWT_PRIVATE_KEY",
+        reason,
+    })
+}
+
+/// Identity providers from `OIDC_PROVIDERS`, a JSON array of provider objects
+fn oidc_providers(source: &Source) -> Result<Vec<OidcProviderConfig>, ConfigError> {
+    let Some(json) = source.optional("OIDC_PROVIDERS") else {
+        return Ok(Vec::new());
+    };
+
+    let invalid = |reason: String| ConfigError::Invalid {
+        key: "OIDC_PROVIDERS",
+        reason,
+    };
+
+    let mut providers: Vec<OidcProviderConfig> =
+        serde_json::from_str(&json).map_err(|e| invalid(e.to_string()))?;
+
+    for provider in providers.iter_mut() {
+        provider.name = provider.name.trim().to_ascii_lowercase();
+        provider.issuer = provider.issuer.trim_end_matches('/').to_string();
+
+        let valid_name = !provider.name.is_empty()
+            && provider.name.len() <= 50
+            && provider
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(invalid(format!(
+                "provider name '{}' must be 1-50 letters, digits, '-' or '_'",
+                provider.name
+            )));
+        }
+
+        let issuer = Url::parse(&provider.issuer)
+            .map_err(|e| invalid(format!("issuer of '{}': {}", provider.name, e)))?;
+        // Plain HTTP is only accepted for a provider on this machine, e.g. a local mock
+        let is_local = matches!(
+            issuer.host_str(),
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+        );
+        if issuer.scheme() != "https" && !(issuer.scheme() == "http" && is_local) {
+            return Err(invalid(format!(
+                "issuer of '{}' must use https",
+                provider.name
+            )));
+        }
+
+        Url::parse(&provider.redirect_uri)
+            .map_err(|e| invalid(format!("redirect_uri of '{}': {}", provider.name, e)))?;
+
+        if !provider.scopes.split_whitespace().any(|s| s == "openid") {
+            return Err(invalid(format!(
+                "scopes of '{}' must include 'openid'",
+                provider.name
+            )));
+        }
+    }
+
+    for (i, provider) in providers.iter().enumerate() {
+        if providers[..i].iter().any(|p| p.name == provider.name) {
+            return Err(invalid(format!("duplicate provider '{}'", provider.name)));
+        }
+    }
+
+    Ok(providers)
+}
+
+/// Argon2id costs from `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and
+/// `ARGON2_PARALLELISM`, defaulting to the OWASP-recommended minimum
+fn argon2_params(source: &Source) -> Result<Params, ConfigError> {
+    let memory_kib = source.parse_or("ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST)?;
+    let iterations = source.parse_or("ARGON2_ITERATIONS", Params::DEFAULT_T_COST)?;
+    let parallelism = source.parse_or("ARGON2_PARALLELISM", Params::DEFAULT_P_COST)?;
+
+    Params::new(memory_kib, iterations, parallelism, None).map_err(|e| {
+        let key = match e {
+            argon2::Error::TimeTooSmall => "ARGON2_ITERATIONS",
+            argon2::Error::ThreadsTooFew | argon2::Error::ThreadsTooMany => "ARGON2_PARALLELISM",
+            _ => "ARGON2_MEMORY_KIB",
+        };
+        ConfigError::Invalid {
+            key,
+            reason: e.to_string(),
+        }
+    })
+}
+
+/// HS256 keys from `JWT_SECRET`, refusing weak or placeholder secrets
+fn hmac_keys(source: &Source) -> Result<JwtKeys, ConfigError> {
+    let secret = source.required("JWT_SECRET")?;
+
+    if secret == INSECURE_JWT_SECRET {
+        return Err(ConfigError::Invalid {
+            key: "JWT_SECRET",
+            reason: "the placeholder secret must not be used".to_string(),
+        });
+    }
+
+    if secret.len() < MIN_JWT_SECRET_LENGTH {
+        return Err(ConfigError::Invalid {
+            key: "JWT_SECRET",
+            reason: format!("must be at least {} bytes long", MIN_JWT_SECRET_LENGTH),
+        });
+    }
+
+    Ok(JwtKeys::hmac(&secret))
+}
+
+/// Read the email transport selected by `EMAIL_TRANSPORT` and its settings
+fn email_transport(source: &Source) -> Result<EmailTransport, ConfigError> {
+    let transport = source
+        .optional("EMAIL_TRANSPORT")
+        .unwrap_or_else(|| "resend".to_string());
+
+    match transport.trim().to_ascii_lowercase().as_str() {
+        "resend" => Ok(EmailTransport::Resend {
+            api_key: source.required("RESEND_API_KEY")?,
+        }),
+        "smtp" => Ok(EmailTransport::Smtp(SmtpConfig {
+            host: source.required("SMTP_HOST")?,
+            port: source.parse_or("SMTP_PORT", 587)?,
+            username: source.optional("SMTP_USERNAME"),
+            password: source.optional("SMTP_PASSWORD"),
+            tls: source.parse_or("SMTP_TLS", SmtpTls::StartTls)?,
+        })),
+        "file" => Ok(EmailTransport::File {
+            dir: source
+                .optional("EMAIL_SINK_DIR")
+                .unwrap_or_else(|| "mail".to_string())
+                .into(),
+        }),
+        "memory" => Ok(EmailTransport::Memory),
+        other => Err(ConfigError::Invalid {
+            key: "EMAIL_TRANSPORT",
+            reason: format!("unknown transport '{}'", other),
+        }),
+    }
+}
+
+/// Wraps a lookup function with typed accessors
+struct Source<'a> {
+    lookup: &'a dyn Fn(&str) -> Option<String>,
+}
+
+impl Source<'_> {
+    fn optional(&self, key: &'static str) -> Option<String> {
+        (self.lookup)(key).filter(|value| !value.trim().is_empty())
+    }
+
+    fn required(&self, key: &'static str) -> Result<String, ConfigError> {
+        self.optional(key).ok_or(ConfigError::Missing(key))
+    }
+
+    fn parse_or<T>(&self, key: &'static str, default: T) -> Result<T, ConfigError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.optional(key) {
+            Some(value) => value
+                .trim()
+                .parse()
+                .map_err(|e: T::Err| ConfigError::Invalid {
+                    key,
+                    reason: e.to_string(),
+                }),
+            None => Ok(default),
+        }
+    }
+}
+
+fn ensure_positive<T>(key: &'static str, value: T) -> Result<(), ConfigError>
+where
+    T: PartialOrd + Default,
+{
+    if value <= T::default() {
+        return Err(ConfigError::Invalid {
+            key,
+            reason: "must be greater than zero".to_string(),
+        });
+    }
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use axum::{
     Json,
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Response},
 };
 use serde_json::json;
@@ -15,7 +15,9 @@ pub enum AppError {
     InvalidCredentials,
     InvalidToken,
     TokenExpired,
+    TokenRevoked,
     Unauthorized,
+    Forbidden(String),
 
     // Validation errors
     ValidationError(String),
@@ -24,9 +26,16 @@ pub enum AppError {
     UserAlreadyExists,
     UserNotFound,
 
+    // Resource errors
+    NotFound(String),
+
     // OTP errors
     InvalidOtp,
     OtpExpired,
+    OtpLocked,
+
+    // Rate limiting
+    TooManyRequests { retry_after_secs: i64 },
 
     // Internal errors
     InternalError(String),
@@ -34,6 +43,11 @@ pub enum AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let retry_after = match &self {
+            AppError::TooManyRequests { retry_after_secs } => Some(*retry_after_secs),
+            _ => None,
+        };
+
         let (status, message) = match self {
             AppError::DatabaseError(e) => {
                 tracing::error!("Database error: {:?}", e);
@@ -48,7 +62,12 @@ impl IntoResponse for AppError {
             ),
             AppError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token".to_string()),
             AppError::TokenExpired => (StatusCode::UNAUTHORIZED, "Token expired".to_string()),
+            AppError::TokenRevoked => (
+                StatusCode::UNAUTHORIZED,
+                "Token has been revoked".to_string(),
+            ),
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             AppError::ValidationError(msg) => {
                 (StatusCode::BAD_REQUEST, msg) // Already a String!
             }
@@ -56,8 +75,20 @@ impl IntoResponse for AppError {
                 (StatusCode::CONFLICT, "User already exists".to_string())
             }
             AppError::UserNotFound => (StatusCode::NOT_FOUND, "User not found".to_string()),
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::InvalidOtp => (StatusCode::BAD_REQUEST, "Invalid OTP code".to_string()),
             AppError::OtpExpired => (StatusCode::BAD_REQUEST, "OTP code expired".to_string()),
+            AppError::OtpLocked => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many incorrect attempts. Please request a new code".to_string(),
+            ),
+            AppError::TooManyRequests { retry_after_secs } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!(
+                    "Too many requests. Please try again in {} seconds",
+                    retry_after_secs
+                ),
+            ),
             AppError::InternalError(msg) => {
                 tracing::error!("Internal error: {}", msg);
                 (
@@ -71,7 +102,10 @@ impl IntoResponse for AppError {
             "error": message,
         }));
 
-        (status, body).into_response()
+        match retry_after {
+            Some(secs) => (status, [(header::RETRY_AFTER, secs.to_string())], body).into_response(),
+            None => (status, body).into_response(),
+        }
     }
 }
 
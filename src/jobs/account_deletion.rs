@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use crate::{
+    models::app_state::AppState,
+    utils::{
+        account_deletion::purge_due_accounts, refresh_token::delete_expired_refresh_tokens,
+        revocation::delete_expired_revocations, throttle::delete_stale_throttles,
+        two_factor::delete_stale_login_challenges,
+    },
+};
+
+/// How often due account deletions are looked for
+const PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Accounts purged per round
+const BATCH_SIZE: i64 = 20;
+
+/// Delete accounts whose grace period is over, and prune expired
+/// authentication records, until the process exits
+pub async fn run_account_deletion_worker(state: AppState) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+    tracing::info!("Account deletion worker started");
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = delete_stale_login_challenges(&state.db).await {
+            tracing::error!("Removing stale login challenges failed: {:?}", e);
+        }
+        if let Err(e) = delete_stale_throttles(&state.db).await {
+            tracing::error!("Removing stale login throttles failed: {:?}", e);
+        }
+        if let Err(e) = delete_expired_revocations(&state.db).await {
+            tracing::error!("Removing expired token revocations failed: {:?}", e);
+        }
+        if let Err(e) = delete_expired_refresh_tokens(&state.db).await {
+            tracing::error!("Removing expired refresh tokens failed: {:?}", e);
+        }
+
+        // Keep going while full batches come back
+        loop {
+            match purge_due_accounts(&state.db, BATCH_SIZE).await {
+                Ok(purged) if purged as i64 == BATCH_SIZE => continue,
+                Ok(_) => break,
+                Err(e) => {
+                    tracing::error!("Account deletion failed: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use crate::{
+    models::app_state::AppState,
+    utils::data_export::{delete_expired_exports, process_next_export},
+};
+
+/// How often pending exports are looked for
+const EXPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Build requested data exports and remove expired ones until the process exits
+pub async fn run_data_export_worker(state: AppState) {
+    let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+
+    tracing::info!("Data export worker started");
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = delete_expired_exports(&state.db).await {
+            tracing::error!("Removing expired data exports failed: {:?}", e);
+        }
+
+        // Keep going while exports are waiting
+        loop {
+            match process_next_export(&state.db, state.config.data_export_ttl_hours).await {
+                Ok(true) => continue,
+                Ok(false) => break,
+                Err(e) => {
+                    tracing::error!("Data export failed: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
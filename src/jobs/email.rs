@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use crate::{
+    mailer::outbox::{self, RetryPolicy},
+    models::app_state::AppState,
+};
+
+/// Messages claimed per delivery round
+const BATCH_SIZE: i64 = 20;
+
+/// Deliver queued email from the outbox until the process exits
+pub async fn run_email_worker(state: AppState) {
+    let policy = RetryPolicy {
+        max_attempts: state.config.email_max_attempts,
+        base_secs: state.config.email_retry_base_secs,
+        max_secs: state.config.email_retry_max_secs,
+    };
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(state.config.email_worker_interval_secs));
+
+    tracing::info!("Email outbox worker started");
+
+    loop {
+        interval.tick().await;
+
+        // Keep going while full batches come back, so a backlog drains quickly
+        loop {
+            match outbox::deliver_due(&state.db, state.mailer.as_ref(), policy, BATCH_SIZE).await {
+                Ok(claimed) if claimed as i64 == BATCH_SIZE => continue,
+                Ok(_) => break,
+                Err(e) => {
+                    tracing::error!("Email outbox delivery failed: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,12 @@
+pub mod account_deletion;
+pub mod data_export;
+pub mod email;
+
+use crate::models::app_state::AppState;
+
+/// Start the background workers; they run for the lifetime of the process
+pub fn spawn_background_jobs(state: AppState) {
+    tokio::spawn(email::run_email_worker(state.clone()));
+    tokio::spawn(account_deletion::run_account_deletion_worker(state.clone()));
+    tokio::spawn(data_export::run_data_export_worker(state));
+}
@@ -0,0 +1,11 @@
+pub mod api;
+pub mod app;
+pub mod config;
+pub mod errors;
+pub mod jobs;
+pub mod mailer;
+pub mod middleware;
+pub mod models;
+pub mod oidc;
+pub mod utils;
+pub mod ws;
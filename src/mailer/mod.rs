@@ -0,0 +1,70 @@
+pub mod outbox;
+pub mod resend;
+pub mod sink;
+pub mod smtp;
+pub mod templates;
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::config::{AppConfig, EmailTransport};
+
+/// A fully rendered outgoing email
+#[derive(Debug, Clone, Serialize)]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub subject: String,
+    pub html: String,
+    /// Plain-text alternative
+    pub text: Option<String>,
+}
+
+/// Error reported by an email transport
+#[derive(Debug)]
+pub struct EmailError(pub String);
+
+impl fmt::Display for EmailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for EmailError {}
+
+/// Delivers outgoing email; held in `AppState` as `Arc<dyn EmailSender>`
+#[async_trait]
+pub trait EmailSender: Send + Sync {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailError>;
+}
+
+/// The configured email transport
+pub struct Mailer {
+    pub sender: Arc<dyn EmailSender>,
+    /// Typed handle on the captured mail with `EMAIL_TRANSPORT=memory`
+    pub memory_sink: Option<sink::MemorySink>,
+}
+
+/// Build the email transport selected by `EMAIL_TRANSPORT`
+pub fn from_config(config: &AppConfig) -> Result<Mailer, EmailError> {
+    let from = config.email_from.clone();
+
+    let mut memory_sink = None;
+    let sender: Arc<dyn EmailSender> = match &config.email_transport {
+        EmailTransport::Resend { api_key } => Arc::new(resend::ResendSender::new(api_key, from)),
+        EmailTransport::Smtp(smtp) => Arc::new(smtp::SmtpSender::new(smtp, from)?),
+        EmailTransport::File { dir } => Arc::new(sink::FileSink::new(dir.clone())),
+        EmailTransport::Memory => {
+            let sink = sink::MemorySink::new();
+            memory_sink = Some(sink.clone());
+            Arc::new(sink)
+        }
+    };
+
+    Ok(Mailer {
+        sender,
+        memory_sink,
+    })
+}
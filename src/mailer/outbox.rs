@@ -0,0 +1,200 @@
+use chrono::{Duration, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use super::{
+    EmailSender, OutgoingEmail,
+    templates::{self, EmailTemplate},
+};
+use crate::errors::AppError;
+
+/// How long a claimed message is reserved for one delivery attempt; a worker
+/// that dies mid-send releases it to the next worker after this
+const CLAIM_LEASE_SECS: i64 = 300;
+
+/// Delivery state of a queued email (`email_outbox.status`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailStatus {
+    Pending,
+    Sent,
+    Dead,
+}
+
+impl EmailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailStatus::Pending => "pending",
+            EmailStatus::Sent => "sent",
+            EmailStatus::Dead => "dead",
+        }
+    }
+}
+
+/// When to retry a failed delivery and when to give up
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Delivery attempts before a message is dead-lettered
+    pub max_attempts: i32,
+    /// Delay before the first retry in seconds
+    pub base_secs: i64,
+    /// Upper bound for the retry delay in seconds
+    pub max_secs: i64,
+}
+
+impl RetryPolicy {
+    /// Delay after the given number of failed attempts: doubles every time
+    fn retry_delay_secs(&self, attempts: i32) -> i64 {
+        let doublings = (attempts - 1).clamp(0, 20) as u32;
+        self.base_secs
+            .saturating_mul(1 << doublings)
+            .min(self.max_secs)
+    }
+}
+
+/// Render a template and queue it for delivery.
+///
+/// Runs on the caller's connection so the email is only sent if the
+/// surrounding transaction commits.
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    user_id: Option<Uuid>,
+    template: EmailTemplate,
+    locale: &str,
+    to: &str,
+    vars: &[(&str, &str)],
+) -> Result<Uuid, AppError> {
+    let email = templates::render(template, locale, to, vars).map_err(|e| {
+        AppError::InternalError(format!("Failed to render {:?} email: {}", template, e))
+    })?;
+
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO email_outbox (user_id, template, recipient, subject, html, text)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
+        "#,
+        user_id,
+        template.name(),
+        email.to,
+        email.subject,
+        email.html,
+        email.text
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(id)
+}
+
+/// Claim up to `batch_size` due messages and try to deliver each once.
+///
+/// Returns the number of messages claimed.
+pub async fn deliver_due(
+    db: &PgPool,
+    mailer: &dyn EmailSender,
+    policy: RetryPolicy,
+    batch_size: i64,
+) -> Result<usize, AppError> {
+    // Claim by pushing next_attempt_at past the lease, so concurrent workers skip them
+    let lease_until = Utc::now() + Duration::seconds(CLAIM_LEASE_SECS);
+    let claimed = sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET attempts = attempts + 1, next_attempt_at = $2, updated_at = NOW()
+        WHERE id IN (
+            SELECT id FROM email_outbox
+            WHERE status = 'pending' AND next_attempt_at <= NOW()
+            ORDER BY next_attempt_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, recipient, subject, html, text, attempts
+        "#,
+        batch_size,
+        lease_until
+    )
+    .fetch_all(db)
+    .await?;
+
+    for message in &claimed {
+        let email = OutgoingEmail {
+            to: message.recipient.clone(),
+            subject: message.subject.clone(),
+            html: message.html.clone(),
+            text: message.text.clone(),
+        };
+
+        match mailer.send(&email).await {
+            Ok(()) => mark_sent(db, message.id).await?,
+            Err(e) => record_failure(db, message.id, message.attempts, &e.0, policy).await?,
+        }
+    }
+
+    Ok(claimed.len())
+}
+
+async fn mark_sent(db: &PgPool, id: Uuid) -> Result<(), AppError> {
+    // Drop the bodies once delivered; they may carry one-time codes
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET status = $2, sent_at = NOW(), last_error = NULL, html = '', text = NULL, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        EmailStatus::Sent.as_str()
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Schedule a retry, or dead-letter the message once attempts are exhausted
+async fn record_failure(
+    db: &PgPool,
+    id: Uuid,
+    attempts: i32,
+    error: &str,
+    policy: RetryPolicy,
+) -> Result<(), AppError> {
+    let (status, next_attempt_at) = if attempts >= policy.max_attempts {
+        tracing::error!(
+            "Giving up on email {} after {} attempts: {}",
+            id,
+            attempts,
+            error
+        );
+        (EmailStatus::Dead, Utc::now())
+    } else {
+        let delay = policy.retry_delay_secs(attempts);
+        tracing::warn!(
+            "Email {} failed (attempt {}), retrying in {}s: {}",
+            id,
+            attempts,
+            delay,
+            error
+        );
+        (EmailStatus::Pending, Utc::now() + Duration::seconds(delay))
+    };
+
+    // Dead-lettered bodies are dropped like delivered ones; they may carry one-time codes
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET status = $2, next_attempt_at = $3, last_error = $4, updated_at = NOW(),
+            html = CASE WHEN $5 THEN '' ELSE html END,
+            text = CASE WHEN $5 THEN NULL ELSE text END
+        WHERE id = $1
+        "#,
+        id,
+        status.as_str(),
+        next_attempt_at,
+        error,
+        status == EmailStatus::Dead
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use resend_rs::{Resend, types::CreateEmailBaseOptions};
+
+use super::{EmailError, EmailSender, OutgoingEmail};
+
+/// Sends email through the Resend API
+pub struct ResendSender {
+    client: Resend,
+    from: String,
+}
+
+impl ResendSender {
+    pub fn new(api_key: &str, from: String) -> Self {
+        Self {
+            client: Resend::new(api_key),
+            from,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailSender for ResendSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailError> {
+        let mut options =
+            CreateEmailBaseOptions::new(&self.from, [email.to.as_str()], &email.subject)
+                .with_html(&email.html);
+
+        if let Some(text) = &email.text {
+            options = options.with_text(text);
+        }
+
+        self.client
+            .emails
+            .send(options)
+            .await
+            .map_err(|e| EmailError(format!("Resend error: {:?}", e)))?;
+
+        Ok(())
+    }
+}
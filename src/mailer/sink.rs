@@ -0,0 +1,82 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::{EmailError, EmailSender, OutgoingEmail};
+
+/// Writes every outgoing email as a JSON file instead of sending it (local development)
+pub struct FileSink {
+    dir: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+#[async_trait]
+impl EmailSender for FileSink {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| EmailError(format!("Failed to create mail directory: {}", e)))?;
+
+        // Timestamp prefix keeps files in send order
+        let file_name = format!(
+            "{}-{}.json",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+            Uuid::new_v4()
+        );
+        let path = self.dir.join(file_name);
+
+        let json = serde_json::to_vec_pretty(email)
+            .map_err(|e| EmailError(format!("Failed to serialize email: {}", e)))?;
+
+        tokio::fs::write(&path, json)
+            .await
+            .map_err(|e| EmailError(format!("Failed to write {}: {}", path.display(), e)))?;
+
+        tracing::info!("Email to {} written to {}", email.to, path.display());
+        Ok(())
+    }
+}
+
+/// Keeps outgoing email in memory so tests can inspect it
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    messages: Arc<Mutex<Vec<OutgoingEmail>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All messages sent so far, oldest first
+    pub async fn messages(&self) -> Vec<OutgoingEmail> {
+        self.messages.lock().await.clone()
+    }
+
+    /// The most recent message sent to an address
+    pub async fn last_message_to(&self, to: &str) -> Option<OutgoingEmail> {
+        self.messages
+            .lock()
+            .await
+            .iter()
+            .rev()
+            .find(|email| email.to == to)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl EmailSender for MemorySink {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailError> {
+        self.messages.lock().await.push(email.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, MultiPart, SinglePart, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+
+use super::{EmailError, EmailSender, OutgoingEmail};
+use crate::config::{SmtpConfig, SmtpTls};
+
+/// Sends email through a plain SMTP server
+pub struct SmtpSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpSender {
+    pub fn new(config: &SmtpConfig, from: String) -> Result<Self, EmailError> {
+        let from = from
+            .parse()
+            .map_err(|e| EmailError(format!("Invalid sender address: {}", e)))?;
+
+        let builder = match config.tls {
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host),
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            SmtpTls::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &config.host,
+            )),
+        }
+        .map_err(|e| EmailError(format!("Invalid SMTP relay: {}", e)))?;
+
+        let mut builder = builder.port(config.port);
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl EmailSender for SmtpSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailError> {
+        let to = email
+            .to
+            .parse()
+            .map_err(|e| EmailError(format!("Invalid recipient address: {}", e)))?;
+
+        let builder = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(&email.subject);
+
+        let message = match &email.text {
+            Some(text) => builder.multipart(MultiPart::alternative_plain_html(
+                text.clone(),
+                email.html.clone(),
+            )),
+            None => builder.singlepart(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_HTML)
+                    .body(email.html.clone()),
+            ),
+        }
+        .map_err(|e| EmailError(format!("Failed to build message: {}", e)))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| EmailError(format!("SMTP error: {}", e)))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use chrono::{Datelike, Utc};
+
+use super::{EmailError, OutgoingEmail};
+
+/// Locale used when neither the user's locale nor the configured default has templates
+pub const BASE_LOCALE: &str = "en";
+
+/// Transactional emails with a template under `templates/email/<locale>/`
+#[derive(Debug, Clone, Copy)]
+pub enum EmailTemplate {
+    /// Account verification code (`code`, `ttl_minutes`)
+    Verification,
+    /// Password reset code (`code`, `ttl_minutes`)
+    PasswordReset,
+    /// Passwordless sign-in code (`code`, `ttl_minutes`)
+    LoginCode,
+    /// Confirmation code sent to a new address (`code`, `ttl_minutes`, `new_email`)
+    EmailChange,
+    /// Heads-up sent to the old address (`new_email`)
+    EmailChangeNotice,
+    /// Notice that the account will be deleted (`deletion_date`)
+    AccountDeletion,
+    /// Invitation to a joint (`inviter`, `joint_name`, `link`)
+    JointInvitation,
+}
+
+impl EmailTemplate {
+    /// File name of the template, also stored in `email_outbox.template`
+    pub fn name(self) -> &'static str {
+        match self {
+            EmailTemplate::Verification => "verification",
+            EmailTemplate::PasswordReset => "password_reset",
+            EmailTemplate::LoginCode => "login_code",
+            EmailTemplate::EmailChange => "email_change",
+            EmailTemplate::EmailChangeNotice => "email_change_notice",
+            EmailTemplate::AccountDeletion => "account_deletion",
+            EmailTemplate::JointInvitation => "joint_invitation",
+        }
+    }
+}
+
+/// Subject, HTML body and plain-text body of one template in one locale
+struct TemplateSource {
+    locale: &'static str,
+    name: &'static str,
+    subject: &'static str,
+    html: &'static str,
+    text: &'static str,
+}
+
+/// Shared HTML and plain-text chrome of one locale; bodies are inserted as `{{ body }}`
+struct LayoutSource {
+    locale: &'static str,
+    html: &'static str,
+    text: &'static str,
+}
+
+macro_rules! template {
+    ($locale:literal, $name:literal) => {
+        TemplateSource {
+            locale: $locale,
+            name: $name,
+            subject: include_str!(concat!(
+                "../../templates/email/",
+                $locale,
+                "/",
+                $name,
+                ".subject"
+            )),
+            html: include_str!(concat!(
+                "../../templates/email/",
+                $locale,
+                "/",
+                $name,
+                ".html"
+            )),
+            text: include_str!(concat!(
+                "../../templates/email/",
+                $locale,
+                "/",
+                $name,
+                ".txt"
+            )),
+        }
+    };
+}
+
+macro_rules! layout {
+    ($locale:literal) => {
+        LayoutSource {
+            locale: $locale,
+            html: include_str!(concat!("../../templates/email/", $locale, "/layout.html")),
+            text: include_str!(concat!("../../templates/email/", $locale, "/layout.txt")),
+        }
+    };
+}
+
+static LAYOUTS: &[LayoutSource] = &[layout!("en"), layout!("es")];
+
+static TEMPLATES: &[TemplateSource] = &[
+    template!("en", "verification"),
+    template!("en", "password_reset"),
+    template!("en", "login_code"),
+    template!("en", "email_change"),
+    template!("en", "email_change_notice"),
+    template!("en", "account_deletion"),
+    template!("en", "joint_invitation"),
+    template!("es", "verification"),
+    template!("es", "password_reset"),
+    template!("es", "login_code"),
+    template!("es", "email_change"),
+    template!("es", "email_change_notice"),
+    template!("es", "account_deletion"),
+    template!("es", "joint_invitation"),
+];
+
+/// Whether templates exist for a locale (exact match, e.g. "es")
+pub fn is_supported_locale(locale: &str) -> bool {
+    LAYOUTS.iter().any(|layout| layout.locale == locale)
+}
+
+/// Pick the locale to render in: the user's locale, then its language
+/// ("es-MX" -> "es"), then the configured default, then `BASE_LOCALE`
+pub fn resolve_locale(locale: Option<&str>, default_locale: &str) -> &'static str {
+    let candidates = locale
+        .into_iter()
+        .flat_map(|locale| [locale, locale.split(['-', '_']).next().unwrap_or(locale)])
+        .chain([default_locale]);
+
+    for candidate in candidates {
+        if let Some(layout) = LAYOUTS
+            .iter()
+            .find(|layout| layout.locale.eq_ignore_ascii_case(candidate))
+        {
+            return layout.locale;
+        }
+    }
+
+    BASE_LOCALE
+}
+
+/// Render a template into a ready-to-send email.
+///
+/// Variables are referenced as `{{ name }}`; values are HTML-escaped in the
+/// HTML part. `year` is always available.
+pub fn render(
+    template: EmailTemplate,
+    locale: &str,
+    to: &str,
+    vars: &[(&str, &str)],
+) -> Result<OutgoingEmail, EmailError> {
+    let source = TEMPLATES
+        .iter()
+        .find(|source| source.locale == locale && source.name == template.name())
+        .or_else(|| {
+            TEMPLATES
+                .iter()
+                .find(|source| source.locale == BASE_LOCALE && source.name == template.name())
+        })
+        .ok_or_else(|| EmailError(format!("missing email template '{}'", template.name())))?;
+
+    let layout = LAYOUTS
+        .iter()
+        .find(|layout| layout.locale == source.locale)
+        .ok_or_else(|| EmailError(format!("missing email layout for '{}'", source.locale)))?;
+
+    let year = Utc::now().year().to_string();
+    let mut vars = vars.to_vec();
+    vars.push(("year", &year));
+
+    let html_body = substitute(source.html, &vars, &[], escape_html)?;
+    let text_body = substitute(source.text, &vars, &[], str::to_string)?;
+
+    // The body is already rendered and escaped, so the layout inserts it verbatim
+    let html = substitute(
+        layout.html,
+        &vars,
+        &[("body", html_body.trim_end())],
+        escape_html,
+    )?;
+    let text = substitute(
+        layout.text,
+        &vars,
+        &[("body", text_body.trim_end())],
+        str::to_string,
+    )?;
+
+    Ok(OutgoingEmail {
+        to: to.to_string(),
+        subject: substitute(source.subject.trim(), &vars, &[], str::to_string)?,
+        html,
+        text: Some(text),
+    })
+}
+
+/// Replace every `{{ name }}` placeholder, encoding `vars` and inserting `raw`
+/// as-is; unknown names are an error so typos in templates surface instead of
+/// sending a broken email
+fn substitute(
+    template: &str,
+    vars: &[(&str, &str)],
+    raw: &[(&str, &str)],
+    encode: impl Fn(&str) -> String,
+) -> Result<String, EmailError> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| EmailError("unterminated placeholder in email template".to_string()))?;
+        let name = after[..end].trim();
+
+        if let Some((_, value)) = raw.iter().find(|(key, _)| *key == name) {
+            output.push_str(value);
+        } else if let Some((_, value)) = vars.iter().find(|(key, _)| *key == name) {
+            output.push_str(&encode(value));
+        } else {
+            return Err(EmailError(format!(
+                "missing email template variable '{}'",
+                name
+            )));
+        }
+        rest = &after[end + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
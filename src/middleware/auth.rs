@@ -1,7 +1,7 @@
 // src/middleware/auth.rs
 use axum::{
     extract::{Request, State},
-    http::{StatusCode, header},
+    http::header,
     middleware::Next,
     response::Response,
 };
@@ -31,40 +31,56 @@ pub async fn auth_middleware(
     let token = auth_header.trim_start_matches("Bearer ");
 
     // Verify token
-    // TODO: Get JWT secret from config
-    let jwt_secret = "your-super-secret-jwt-key-change-in-production";
-
-    let claims = verify_token(token, jwt_secret)?;
+    let claims = verify_token(token, &state.config.jwt_keys)?;
 
     // Parse user_id from claims.sub
     let user_id = Uuid::parse_str(&claims.sub).map_err(|_| AppError::InvalidToken)?;
 
-    // Add user_id to request extensions
+    // Reject tokens revoked by logout / logout-all
+    if state
+        .revocations
+        .is_revoked(&state.db, user_id, &claims)
+        .await?
+    {
+        return Err(AppError::TokenRevoked);
+    }
+
+    // Add user_id and claims to request extensions
     req.extensions_mut().insert(user_id);
+    req.extensions_mut().insert(claims);
 
     Ok(next.run(req).await)
 }
 
 /// Optional auth - doesn't fail if no token, just doesn't add user_id
 pub async fn optional_auth_middleware(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     mut req: Request,
     next: Next,
 ) -> Response {
-    // Try to get Authorization header
-    if let Some(auth_header) = req.headers().get(header::AUTHORIZATION) {
-        if let Ok(auth_str) = auth_header.to_str() {
-            if auth_str.starts_with("Bearer ") {
-                let token = auth_str.trim_start_matches("Bearer ");
-                let jwt_secret = "your-super-secret-jwt-key-change-in-production";
+    // Try to get a bearer token from the Authorization header
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+
+    // Try to verify token and parse user_id
+    let verified = token
+        .and_then(|token| verify_token(token, &state.config.jwt_keys).ok())
+        .and_then(|claims| Some((Uuid::parse_str(&claims.sub).ok()?, claims)));
+
+    if let Some((user_id, claims)) = verified {
+        // Revoked tokens are treated as anonymous; lookup errors too
+        let revoked = state
+            .revocations
+            .is_revoked(&state.db, user_id, &claims)
+            .await
+            .unwrap_or(true);
 
-                // Try to verify token and parse user_id
-                if let Ok(claims) = verify_token(token, jwt_secret) {
-                    if let Ok(user_id) = Uuid::parse_str(&claims.sub) {
-                        req.extensions_mut().insert(user_id);
-                    }
-                }
-            }
+        if !revoked {
+            req.extensions_mut().insert(user_id);
+            req.extensions_mut().insert(claims);
         }
     }
 
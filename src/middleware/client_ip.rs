@@ -0,0 +1,52 @@
+// src/middleware/client_ip.rs
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::request::Parts,
+};
+
+use crate::models::app_state::AppState;
+
+/// Client IP address, as seen by the outermost trusted proxy.
+///
+/// Each proxy appends its peer to `X-Forwarded-For`, so with
+/// `TRUSTED_PROXY_HOPS = n` the n-th entry from the right is the client;
+/// anything left of it was sent by the client and can be forged. Without
+/// trusted proxies the socket peer address is used.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let hops = state.config.trusted_proxy_hops;
+
+        let forwarded = || {
+            parts
+                .headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|h| h.to_str().ok())
+                .flat_map(|h| h.split(','))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .nth(hops.checked_sub(1)?)
+                .and_then(|ip| ip.trim().parse().ok())
+        };
+
+        let peer = || {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        };
+
+        Ok(ClientIp(forwarded().or_else(peer)))
+    }
+}
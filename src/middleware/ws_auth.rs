@@ -7,13 +7,30 @@ use axum::{
 use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::{errors::AppError, models::app_state::AppState, utils::jwt::verify_token};
+use crate::{
+    errors::AppError,
+    models::app_state::AppState,
+    utils::{
+        joint_bans::ensure_not_banned,
+        jwt::{Claims, verify_token},
+    },
+};
 
 #[derive(Deserialize)]
 pub struct WsQuery {
     token: String,
 }
 
+/// Authenticated WebSocket session, added to request extensions
+#[derive(Debug, Clone)]
+pub struct WsSession {
+    pub user_id: Uuid,
+    pub joint_id: Uuid,
+    pub username: String,
+    /// Access token used to connect; its `jti` closes the socket on logout
+    pub claims: Claims,
+}
+
 /// WebSocket authentication middleware - extracts user info and joint_id from query param
 pub async fn ws_auth_middleware(
     State(state): State<AppState>,
@@ -23,18 +40,30 @@ pub async fn ws_auth_middleware(
     next: Next,
 ) -> Result<Response, AppError> {
     // Verify token from query parameter
-    let jwt_secret = "your-super-secret-jwt-key-change-in-production";
-    let claims = verify_token(&query.token, jwt_secret)?;
+    let claims = verify_token(&query.token, &state.config.jwt_keys)?;
 
     // Parse user_id from claims
     let user_id = Uuid::parse_str(&claims.sub).map_err(|_| AppError::InvalidToken)?;
 
+    // Reject tokens revoked by logout / logout-all
+    if state
+        .revocations
+        .is_revoked(&state.db, user_id, &claims)
+        .await?
+    {
+        return Err(AppError::TokenRevoked);
+    }
+
     // Get username from database
     let user = sqlx::query!("SELECT username FROM users WHERE id = $1", user_id)
         .fetch_optional(&state.db)
         .await?
         .ok_or(AppError::Unauthorized)?;
 
+    // Banning removes the membership; report the ban rather than that
+    let mut conn = state.db.acquire().await?;
+    ensure_not_banned(&mut conn, joint_id, user_id).await?;
+
     // Verify user is a member of the joint
     let is_member = sqlx::query!(
         "SELECT id FROM joint_members WHERE joint_id = $1 AND user_id = $2",
@@ -45,15 +74,27 @@ pub async fn ws_auth_middleware(
     .await?;
 
     if is_member.is_none() {
-        return Err(AppError::ValidationError(
+        return Err(AppError::Forbidden(
             "You are not a member of this joint".to_string(),
         ));
     }
 
-    // Add user_id, joint_id, and username to extensions
-    req.extensions_mut().insert(user_id);
-    req.extensions_mut().insert(joint_id);
-    req.extensions_mut().insert(user.username);
+    // Closed joints keep their history but take no new connections
+    let is_active = sqlx::query_scalar!("SELECT is_active FROM joints WHERE id = $1", joint_id)
+        .fetch_one(&state.db)
+        .await?;
+
+    if !is_active {
+        return Err(AppError::Forbidden("This joint is closed".to_string()));
+    }
+
+    // Add session info to extensions
+    req.extensions_mut().insert(WsSession {
+        user_id,
+        joint_id,
+        username: user.username,
+        claims,
+    });
 
     Ok(next.run(req).await)
 }
@@ -1,20 +1,39 @@
+use crate::config::AppConfig;
+use crate::mailer::{EmailSender, Mailer, sink::MemorySink};
+use crate::oidc::OidcClient;
+use crate::utils::revocation::RevocationCache;
 use crate::ws::manager::ConnectionManager;
 use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Application state shared across handlers
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
-    pub resend_api_key: String,
+    pub config: Arc<AppConfig>,
+    pub mailer: Arc<dyn EmailSender>,
+    /// Mail captured with `EMAIL_TRANSPORT=memory`, for tests to inspect
+    pub memory_sink: Option<MemorySink>,
     pub ws_manager: ConnectionManager,
+    pub revocations: RevocationCache,
+    pub oidc: OidcClient,
 }
 
 impl AppState {
-    pub fn new(db: PgPool, resend_api_key: String) -> Self {
+    pub fn new(db: PgPool, config: AppConfig, mailer: Mailer) -> Self {
+        let revocations =
+            RevocationCache::new(Duration::from_secs(config.revocation_cache_ttl_secs));
+        let oidc = OidcClient::new(&config.oidc_providers);
+
         Self {
             db,
-            resend_api_key,
+            config: Arc::new(config),
+            mailer: mailer.sender,
+            memory_sink: mailer.memory_sink,
             ws_manager: ConnectionManager::new(),
+            revocations,
+            oidc,
         }
     }
 }
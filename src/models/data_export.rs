@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// A data export was queued
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DataExportRequestedResponse {
+    /// Export identifier, for /api/v1/profile/exports/{id}
+    pub id: Uuid,
+    /// Downloads the archive once it is ready; shown only once
+    pub download_token: String,
+    /// Result message
+    pub message: String,
+}
+
+/// Progress of a data export
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct DataExportStatusResponse {
+    pub id: Uuid,
+    /// pending, processing, ready or failed
+    #[schema(example = "ready")]
+    pub status: String,
+    /// When the export was requested
+    pub created_at: DateTime<Utc>,
+    /// When the archive was built, or the export failed
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Until when the archive can be downloaded
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Download token of a data export
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DataExportDownloadQuery {
+    /// Token returned when the export was requested
+    pub token: String,
+}
@@ -0,0 +1,16 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Delivery status of a queued email.
+///
+/// The endpoint needs no login, so only the status is shown.
+#[derive(Debug, Clone, FromRow, Serialize, ToSchema)]
+pub struct EmailStatusResponse {
+    /// Email identifier, as returned when the email was queued
+    pub id: Uuid,
+    /// pending, sent or dead (delivery abandoned)
+    #[schema(example = "sent")]
+    pub status: String,
+}
@@ -10,7 +10,8 @@ use validator::Validate;
 pub struct Joint {
     pub id: Uuid,
     pub name: String,
-    pub creator_id: Uuid,
+    /// None once the creator has deleted their account
+    pub creator_id: Option<Uuid>,
     pub joint_type: String,
     pub visibility: String,
     pub latitude: f64,
@@ -24,6 +25,35 @@ pub struct Joint {
     pub is_active: Option<bool>,
 }
 
+/// Role of a member in a joint, as stored in `joint_members.role`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JointRole {
+    Creator,
+    Moderator,
+    Member,
+}
+
+impl JointRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JointRole::Creator => "creator",
+            JointRole::Moderator => "moderator",
+            JointRole::Member => "member",
+        }
+    }
+
+    /// Parse a stored role; unknown values are None
+    pub fn parse(role: &str) -> Option<Self> {
+        match role {
+            "creator" => Some(JointRole::Creator),
+            "moderator" => Some(JointRole::Moderator),
+            "member" => Some(JointRole::Member),
+            _ => None,
+        }
+    }
+}
+
 /// Create a new joint
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct CreateJointRequest {
@@ -125,3 +155,86 @@ pub struct JoinJointResponse {
     /// Whether join was successful
     pub joined: bool,
 }
+
+/// A joint with its member count and the caller's role
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JointDetailResponse {
+    #[serde(flatten)]
+    pub joint: Joint,
+    /// Current member count
+    pub member_count: i64,
+    /// Caller's role, absent when not a member
+    pub role: Option<JointRole>,
+}
+
+/// Update a joint; fields left out stay unchanged
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdateJointRequest {
+    /// Joint name (3-100 characters)
+    #[validate(length(min = 3, max = 100))]
+    #[schema(example = "Coffee Lovers Uptown")]
+    pub name: Option<String>,
+
+    /// Description; an empty string removes it
+    #[validate(length(max = 500))]
+    #[schema(example = "Now meeting at the corner cafe")]
+    pub description: Option<String>,
+
+    /// Radius in meters (10-5000)
+    #[validate(range(min = 10, max = 5000))]
+    #[schema(example = 300)]
+    pub radius: Option<i32>,
+}
+
+/// Result of a joint management action
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JointActionResponse {
+    /// Result message
+    pub message: String,
+}
+
+/// Select a member of a joint
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct JointMemberRequest {
+    /// The member's user ID
+    pub user_id: Uuid,
+}
+
+/// Result of a role change
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RoleChangeResponse {
+    /// Member whose role changed
+    pub user_id: Uuid,
+    /// Their new role
+    pub role: JointRole,
+    /// Result message
+    pub message: String,
+}
+
+/// Ban a user from a joint
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct BanMemberRequest {
+    /// The user's ID; they need not be a member
+    pub user_id: Uuid,
+
+    /// Reason shown to the banned user
+    #[validate(length(min = 1, max = 500))]
+    #[schema(example = "Spamming the chat")]
+    pub reason: Option<String>,
+
+    /// Ban duration in hours (1-8760); permanent when left out
+    #[validate(range(min = 1, max = 8760))]
+    #[schema(example = 24)]
+    pub expires_in_hours: Option<i32>,
+}
+
+/// Ban result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BanResponse {
+    /// Banned user
+    pub user_id: Uuid,
+    /// End of the ban, absent when permanent
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Result message
+    pub message: String,
+}
@@ -1,4 +1,8 @@
 pub mod app_state;
+pub mod data_export;
+pub mod email;
 pub mod joint;
+pub mod oidc;
 pub mod profile;
+pub mod two_factor;
 pub mod user;
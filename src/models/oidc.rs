@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::models::user::validate_locale;
+
+/// Identity providers available for sign-in
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OidcProvidersResponse {
+    /// Provider names, for /api/v1/auth/oidc/{provider}/authorize
+    #[schema(example = json!(["apple", "google"]))]
+    pub providers: Vec<String>,
+}
+
+/// Where to send the user to sign in at the provider
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OidcAuthorizeResponse {
+    /// Open in a browser; the provider redirects back with `code` and `state`
+    pub authorization_url: String,
+    /// Opaque value that comes back with the redirect; pass it to the callback
+    pub state: String,
+    /// Time to complete the sign-in, in seconds
+    pub expires_in: i64,
+}
+
+/// Finish a sign-in with the values the provider redirected back with
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct OidcCallbackRequest {
+    /// Authorization code from the redirect
+    #[validate(length(min = 1, max = 2048))]
+    pub code: String,
+
+    /// State from the redirect
+    #[validate(length(min = 1, max = 128))]
+    pub state: String,
+
+    /// Required when the sign-in creates a new account: must be 18 years or older
+    #[schema(example = true)]
+    pub is_18_plus: Option<bool>,
+
+    /// Preferred language for emails of a new account (e.g. "en", "es-MX")
+    #[validate(custom(function = "validate_locale"))]
+    #[schema(example = "en")]
+    pub locale: Option<String>,
+}
@@ -14,6 +14,9 @@ pub struct UserProfile {
     pub display_name: Option<String>,
     pub profile_picture_url: Option<String>,
     pub phone_number: Option<String>,
+    pub locale: Option<String>,
+    /// New email awaiting confirmation, if an email change is in progress
+    pub pending_email: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -30,6 +33,11 @@ pub struct UpdateProfileRequest {
     #[validate(length(max = 12))]
     #[schema(example = "+1234567890")]
     pub phone_number: Option<String>,
+
+    /// Preferred language for emails
+    #[validate(custom(function = "crate::models::user::validate_locale"))]
+    #[schema(example = "es")]
+    pub locale: Option<String>,
 }
 
 /// Profile response
@@ -37,3 +45,61 @@ pub struct UpdateProfileRequest {
 pub struct ProfileResponse {
     pub profile: UserProfile,
 }
+
+/// Request to change the account email address
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ChangeEmailRequest {
+    /// New email address; a confirmation code is sent to it
+    #[validate(email)]
+    #[schema(example = "john.new@example.com")]
+    pub new_email: String,
+
+    /// Current password
+    #[validate(length(min = 1))]
+    #[schema(example = "SecurePass123!")]
+    pub password: String,
+}
+
+/// Email change request result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChangeEmailResponse {
+    /// Address awaiting confirmation
+    pub pending_email: String,
+    /// Queued confirmation email, see `GET /api/v1/emails/{id}`
+    pub email_id: Uuid,
+    /// Result message
+    pub message: String,
+}
+
+/// Confirm an email change with the code sent to the new address
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct VerifyEmailChangeRequest {
+    /// 6-digit code
+    #[validate(length(equal = 6))]
+    #[schema(example = "123456")]
+    pub code: String,
+}
+
+/// Request to delete the account, confirmed with the current credentials
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct DeleteAccountRequest {
+    /// Current password; accounts created through an identity provider set
+    /// one with forgot-password first
+    #[validate(length(min = 1))]
+    #[schema(example = "SecurePass123!")]
+    pub password: String,
+
+    /// Code from the authenticator app, or a recovery code; required with 2FA enabled
+    #[validate(length(min = 1))]
+    #[schema(example = "123456")]
+    pub code: Option<String>,
+}
+
+/// Account deletion request result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeleteAccountResponse {
+    /// When the account and its personal data are removed
+    pub deletion_scheduled_at: DateTime<Utc>,
+    /// Result message
+    pub message: String,
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Start enrolling an authenticator app
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorSetupRequest {
+    /// Current password
+    #[validate(length(min = 1))]
+    #[schema(example = "SecurePass123!")]
+    pub password: String,
+}
+
+/// Secret to add to an authenticator app
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorSetupResponse {
+    /// Base32 secret, for manual entry
+    #[schema(example = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP")]
+    pub secret: String,
+    /// Provisioning URI, usually rendered as a QR code
+    #[schema(
+        example = "otpauth://totp/Spot%20Feed:johndoe?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=Spot%20Feed&algorithm=SHA1&digits=6&period=30"
+    )]
+    pub otpauth_uri: String,
+    /// Next step
+    pub message: String,
+}
+
+/// Confirm enrollment with a first code from the authenticator app
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorConfirmRequest {
+    /// 6-digit code from the authenticator app
+    #[validate(length(equal = 6))]
+    #[schema(example = "123456")]
+    pub code: String,
+}
+
+/// Recovery codes, shown only once
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    /// Single-use codes that replace a TOTP code when the authenticator is lost
+    #[schema(example = json!(["k7m2p-x9qrt", "a3hw8-zn4ve"]))]
+    pub recovery_codes: Vec<String>,
+    /// Result message
+    pub message: String,
+}
+
+/// Turn off two-factor authentication
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorDisableRequest {
+    /// Current password
+    #[validate(length(min = 1))]
+    #[schema(example = "SecurePass123!")]
+    pub password: String,
+
+    /// Code from the authenticator app, or a recovery code
+    #[validate(length(min = 1))]
+    #[schema(example = "123456")]
+    pub code: String,
+}
+
+/// Turn off result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorDisableResponse {
+    /// Result message
+    pub message: String,
+}
+
+/// Returned by login instead of tokens when 2FA is enabled
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorChallengeResponse {
+    /// Pass to /api/v1/auth/2fa/verify along with a code
+    pub challenge_token: String,
+    /// Challenge lifetime in seconds
+    pub expires_in: i64,
+    /// Next step
+    pub message: String,
+}
+
+/// Second login step
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct TwoFactorVerifyRequest {
+    /// Challenge token returned by login
+    #[validate(length(min = 1))]
+    pub challenge_token: String,
+
+    /// Code from the authenticator app, or a recovery code
+    #[validate(length(min = 1))]
+    #[schema(example = "123456")]
+    pub code: String,
+}
@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use crate::utils::normalize::is_email_identifier;
 
 /// User from database
 #[derive(Debug, Clone, FromRow, Serialize)]
@@ -17,6 +19,7 @@ pub struct User {
     pub phone: Option<String>,
     pub is_18_plus: bool,
     pub is_verified: bool,
+    pub locale: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -24,8 +27,8 @@ pub struct User {
 /// Register a new user account
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct RegisterRequest {
-    /// Username (3-50 characters)
-    #[validate(length(min = 3, max = 50))]
+    /// Username (3-50 characters, no "@"; unique regardless of case)
+    #[validate(length(min = 3, max = 50), custom(function = "validate_username"))]
     #[schema(example = "johndoe")]
     pub username: String,
 
@@ -34,14 +37,19 @@ pub struct RegisterRequest {
     #[schema(example = "john@example.com")]
     pub email: String,
 
-    /// Password (minimum 8 characters)
-    #[validate(length(min = 8))]
-    #[schema(example = "SecurePass123!")]
+    /// Password, checked against the password policy (by default at least
+    /// 8 characters, not a common password, not containing the username or email)
+    #[schema(example = "Tangerine-Harbor-42")]
     pub password: String,
 
     /// Must be 18 years or older
     #[schema(example = true)]
     pub is_18_plus: bool,
+
+    /// Preferred language for emails (e.g. "en", "es-MX")
+    #[validate(custom(function = "validate_locale"))]
+    #[schema(example = "en")]
+    pub locale: Option<String>,
 }
 
 /// Successful registration response
@@ -53,17 +61,20 @@ pub struct RegisterResponse {
     pub username: String,
     /// Email address
     pub email: String,
+    /// Queued verification email, see `GET /api/v1/emails/{id}`
+    pub email_id: Uuid,
     /// Success message
     pub message: String,
 }
 
-/// Login with username and password
+/// Login with email or username and password
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct LoginRequest {
-    /// Your username
-    #[validate(length(min = 1))]
+    /// Your email address or username (case-insensitive)
+    #[serde(alias = "username", alias = "email")]
+    #[validate(length(min = 1, max = 255))]
     #[schema(example = "johndoe")]
-    pub username: String,
+    pub identifier: String,
 
     /// Your password
     #[validate(length(min = 1))]
@@ -71,6 +82,36 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+/// Request a passwordless sign-in code
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct LoginCodeRequest {
+    /// Email address of the account
+    #[validate(email)]
+    #[schema(example = "john@example.com")]
+    pub email: String,
+}
+
+/// Sign-in code request result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LoginCodeResponse {
+    /// Result message
+    pub message: String,
+}
+
+/// Sign in with an emailed code instead of a password
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct VerifyLoginCodeRequest {
+    /// Email address of the account
+    #[validate(email)]
+    #[schema(example = "john@example.com")]
+    pub email: String,
+
+    /// 6-digit sign-in code
+    #[validate(length(equal = 6))]
+    #[schema(example = "123456")]
+    pub code: String,
+}
+
 /// Successful login response with JWT token
 #[derive(Debug, Serialize, ToSchema)]
 pub struct LoginResponse {
@@ -80,6 +121,18 @@ pub struct LoginResponse {
     pub token_type: String,
     /// Token expiration time in seconds
     pub expires_in: i64,
+    /// Opaque refresh token, exchange at /api/v1/auth/refresh
+    pub refresh_token: String,
+    /// Refresh token expiration time in seconds
+    pub refresh_expires_in: i64,
+}
+
+/// Exchange a refresh token for a new token pair
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct RefreshTokenRequest {
+    /// Refresh token from the last login or refresh
+    #[validate(length(min = 1))]
+    pub refresh_token: String,
 }
 
 /// Verify OTP code sent to email
@@ -104,3 +157,113 @@ pub struct VerifyOtpResponse {
     /// Whether verification was successful
     pub verified: bool,
 }
+
+/// Request a new email verification code
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ResendOtpRequest {
+    /// Email address used during registration
+    #[validate(email)]
+    #[schema(example = "john@example.com")]
+    pub email: String,
+}
+
+/// Resend OTP result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResendOtpResponse {
+    /// Queued verification email, see `GET /api/v1/emails/{id}`
+    pub email_id: Uuid,
+    /// Result message
+    pub message: String,
+}
+
+/// Request a password reset code
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ForgotPasswordRequest {
+    /// Email address of the account
+    #[validate(email)]
+    #[schema(example = "john@example.com")]
+    pub email: String,
+}
+
+/// Password reset request result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ForgotPasswordResponse {
+    /// Result message
+    pub message: String,
+}
+
+/// Reset password with an emailed code
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ResetPasswordRequest {
+    /// Email address of the account
+    #[validate(email)]
+    #[schema(example = "john@example.com")]
+    pub email: String,
+
+    /// 6-digit reset code
+    #[validate(length(equal = 6))]
+    #[schema(example = "123456")]
+    pub code: String,
+
+    /// New password, checked against the password policy
+    #[schema(example = "Quiet-Lantern-Meadow")]
+    pub new_password: String,
+}
+
+/// Password reset result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResetPasswordResponse {
+    /// Result message
+    pub message: String,
+}
+
+/// Change the password of the signed-in account
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ChangePasswordRequest {
+    /// Current password
+    #[validate(length(min = 1))]
+    #[schema(example = "SecurePass123!")]
+    pub current_password: String,
+
+    /// New password, checked against the password policy
+    #[schema(example = "Quiet-Lantern-Meadow")]
+    pub new_password: String,
+}
+
+/// Logout of the current session
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct LogoutRequest {
+    /// Refresh token of this session, revoked along with the access token
+    pub refresh_token: Option<String>,
+}
+
+/// Logout result
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LogoutResponse {
+    /// Result message
+    pub message: String,
+}
+
+/// Usernames must not look like email addresses, which log in by email
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if is_email_identifier(username) {
+        return Err(
+            ValidationError::new("username").with_message("Username must not contain '@'".into())
+        );
+    }
+    Ok(())
+}
+
+/// Accept locale tags like "en", "es" or "pt-BR"
+pub fn validate_locale(locale: &str) -> Result<(), ValidationError> {
+    let valid = (2..=35).contains(&locale.len())
+        && locale
+            .split(['-', '_'])
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("locale"))
+    }
+}
@@ -0,0 +1,169 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::JwkSet;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use super::OidcError;
+use super::id_token::{self, IdTokenClaims};
+use crate::config::OidcProviderConfig;
+
+/// How long a discovery document and its keys are used before refetching
+const METADATA_TTL: Duration = Duration::from_secs(3600);
+
+/// Minimum delay between two key refreshes caused by an unknown key id
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The parts of an OpenID Provider's discovery document we rely on
+#[derive(Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+struct Cached {
+    metadata: Arc<ProviderMetadata>,
+    jwks: Arc<JwkSet>,
+    fetched_at: Instant,
+    jwks_fetched_at: Instant,
+}
+
+/// A configured provider with its lazily fetched metadata and signing keys
+pub struct ProviderCache {
+    pub config: OidcProviderConfig,
+    cached: RwLock<Option<Cached>>,
+}
+
+impl ProviderCache {
+    pub fn new(config: OidcProviderConfig) -> Self {
+        Self {
+            config,
+            cached: RwLock::new(None),
+        }
+    }
+
+    /// Discovery document, fetched on first use and refreshed after `METADATA_TTL`
+    pub async fn metadata(
+        &self,
+        http: &reqwest::Client,
+    ) -> Result<Arc<ProviderMetadata>, OidcError> {
+        Ok(self.load(http).await?.0)
+    }
+
+    /// Validate an ID token issued by this provider for our client.
+    ///
+    /// A token signed with a key we have not seen yet triggers one refresh of
+    /// the provider's key set, so key rotation at the provider just works.
+    pub async fn validate_id_token(
+        &self,
+        http: &reqwest::Client,
+        token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, OidcError> {
+        let (metadata, mut jwks) = self.load(http).await?;
+
+        if !id_token::has_signing_key(token, &jwks)? {
+            jwks = self.refresh_jwks(http).await?;
+        }
+
+        id_token::validate(
+            token,
+            &jwks,
+            &metadata.issuer,
+            &self.config.client_id,
+            nonce,
+        )
+    }
+
+    async fn load(
+        &self,
+        http: &reqwest::Client,
+    ) -> Result<(Arc<ProviderMetadata>, Arc<JwkSet>), OidcError> {
+        if let Some(cached) = self.cached.read().await.as_ref()
+            && cached.fetched_at.elapsed() < METADATA_TTL
+        {
+            return Ok((cached.metadata.clone(), cached.jwks.clone()));
+        }
+
+        let mut cached = self.cached.write().await;
+        // Another request may have refreshed the cache while we waited
+        if let Some(cached) = cached.as_ref()
+            && cached.fetched_at.elapsed() < METADATA_TTL
+        {
+            return Ok((cached.metadata.clone(), cached.jwks.clone()));
+        }
+
+        let metadata = Arc::new(discover(http, &self.config.issuer).await?);
+        let jwks = Arc::new(fetch_jwks(http, &metadata.jwks_uri).await?);
+        tracing::info!(
+            "Loaded OIDC metadata of {} ({} signing keys)",
+            self.config.name,
+            jwks.keys.len()
+        );
+
+        let now = Instant::now();
+        *cached = Some(Cached {
+            metadata: metadata.clone(),
+            jwks: jwks.clone(),
+            fetched_at: now,
+            jwks_fetched_at: now,
+        });
+
+        Ok((metadata, jwks))
+    }
+
+    async fn refresh_jwks(&self, http: &reqwest::Client) -> Result<Arc<JwkSet>, OidcError> {
+        let mut guard = self.cached.write().await;
+        let Some(cached) = guard.as_mut() else {
+            return Err(OidcError::Provider(
+                "metadata not loaded before refreshing keys".to_string(),
+            ));
+        };
+
+        // Unknown key ids are attacker-controlled; do not refetch on every request
+        if cached.jwks_fetched_at.elapsed() < JWKS_REFRESH_INTERVAL {
+            return Ok(cached.jwks.clone());
+        }
+
+        cached.jwks = Arc::new(fetch_jwks(http, &cached.metadata.jwks_uri).await?);
+        cached.jwks_fetched_at = Instant::now();
+        tracing::info!("Refreshed OIDC signing keys of {}", self.config.name);
+
+        Ok(cached.jwks.clone())
+    }
+}
+
+/// Fetch `<issuer>/.well-known/openid-configuration`
+async fn discover(http: &reqwest::Client, issuer: &str) -> Result<ProviderMetadata, OidcError> {
+    let url = format!("{}/.well-known/openid-configuration", issuer);
+    let metadata: ProviderMetadata = http
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    // The document must describe the issuer it was fetched from
+    if metadata.issuer.trim_end_matches('/') != issuer {
+        return Err(OidcError::Provider(format!(
+            "discovery document at {} is for issuer {}",
+            url, metadata.issuer
+        )));
+    }
+
+    Ok(metadata)
+}
+
+async fn fetch_jwks(http: &reqwest::Client, jwks_uri: &str) -> Result<JwkSet, OidcError> {
+    Ok(http
+        .get(jwks_uri)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
@@ -0,0 +1,139 @@
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use serde::{Deserialize, Deserializer};
+use subtle::ConstantTimeEq;
+
+use super::OidcError;
+
+/// Clock skew tolerated between us and the provider, in seconds
+const LEEWAY_SECS: u64 = 60;
+
+/// Claims of a validated ID token that account linking needs
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    /// Stable identifier of the user at the provider
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    /// Some providers send this as the string "true"
+    #[serde(default, deserialize_with = "bool_or_string")]
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub name: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// Email address the provider vouches for, if any
+    pub fn verified_email(&self) -> Option<&str> {
+        self.email
+            .as_deref()
+            .filter(|email| self.email_verified && !email.trim().is_empty())
+    }
+}
+
+fn bool_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+
+    Ok(match Option::<BoolOrString>::deserialize(deserializer)? {
+        Some(BoolOrString::Bool(value)) => value,
+        Some(BoolOrString::String(value)) => value.eq_ignore_ascii_case("true"),
+        None => false,
+    })
+}
+
+/// Whether the key set holds the key the token claims to be signed with
+pub fn has_signing_key(token: &str, jwks: &JwkSet) -> Result<bool, OidcError> {
+    let header = decode_header(token).map_err(|e| OidcError::InvalidIdToken(e.to_string()))?;
+
+    Ok(match header.kid {
+        Some(kid) => jwks.find(&kid).is_some(),
+        None => jwks.keys.len() == 1,
+    })
+}
+
+/// Check the signature, issuer, audience, expiry and nonce of an ID token
+pub fn validate(
+    token: &str,
+    jwks: &JwkSet,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, OidcError> {
+    let invalid = |reason: String| OidcError::InvalidIdToken(reason);
+
+    let header = decode_header(token).map_err(|e| invalid(e.to_string()))?;
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        // Without a kid the provider must publish exactly one key
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+    .ok_or_else(|| invalid(format!("no signing key for kid {:?}", header.kid)))?;
+
+    let algorithm = signing_algorithm(jwk, header.alg).ok_or_else(|| {
+        invalid(format!(
+            "algorithm {:?} does not match the signing key",
+            header.alg
+        ))
+    })?;
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| invalid(e.to_string()))?;
+
+    let mut validation = Validation::new(algorithm);
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    validation.leeway = LEEWAY_SECS;
+
+    let claims = decode::<IdTokenClaims>(token, &key, &validation)
+        .map_err(|e| invalid(e.to_string()))?
+        .claims;
+
+    // The nonce ties the token to the sign-in we started
+    let nonce_matches = claims
+        .nonce
+        .as_deref()
+        .is_some_and(|claimed| bool::from(claimed.as_bytes().ct_eq(nonce.as_bytes())));
+    if !nonce_matches {
+        return Err(invalid("nonce mismatch".to_string()));
+    }
+
+    Ok(claims)
+}
+
+/// The algorithm to verify with: the one pinned by the key, or the token's
+/// own as long as it fits the key type. Symmetric algorithms are never accepted.
+fn signing_algorithm(jwk: &Jwk, token_alg: Algorithm) -> Option<Algorithm> {
+    if matches!(jwk.algorithm, AlgorithmParameters::OctetKey(_)) {
+        return None;
+    }
+
+    if let Some(key_alg) = jwk.common.key_algorithm {
+        let key_alg = key_alg.to_string().parse::<Algorithm>().ok()?;
+        return (key_alg == token_alg).then_some(key_alg);
+    }
+
+    let fits = match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => matches!(
+            token_alg,
+            Algorithm::RS256
+                | Algorithm::RS384
+                | Algorithm::RS512
+                | Algorithm::PS256
+                | Algorithm::PS384
+                | Algorithm::PS512
+        ),
+        AlgorithmParameters::EllipticCurve(params) => matches!(
+            (&params.curve, token_alg),
+            (EllipticCurve::P256, Algorithm::ES256) | (EllipticCurve::P384, Algorithm::ES384)
+        ),
+        AlgorithmParameters::OctetKeyPair(_) => token_alg == Algorithm::EdDSA,
+        AlgorithmParameters::OctetKey(_) => false,
+    };
+
+    fits.then_some(token_alg)
+}
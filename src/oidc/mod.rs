@@ -0,0 +1,192 @@
+pub mod discovery;
+pub mod id_token;
+pub mod pkce;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::config::OidcProviderConfig;
+use crate::errors::AppError;
+
+use discovery::ProviderCache;
+use id_token::IdTokenClaims;
+
+/// Timeout for every request to an identity provider
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Error raised while talking to an identity provider
+#[derive(Debug)]
+pub enum OidcError {
+    /// The provider could not be reached or answered with something unusable
+    Provider(String),
+    /// The ID token failed validation
+    InvalidIdToken(String),
+}
+
+impl fmt::Display for OidcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OidcError::Provider(reason) => write!(f, "identity provider error: {}", reason),
+            OidcError::InvalidIdToken(reason) => write!(f, "invalid ID token: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for OidcError {}
+
+impl From<OidcError> for AppError {
+    fn from(e: OidcError) -> Self {
+        match e {
+            OidcError::Provider(_) => AppError::InternalError(e.to_string()),
+            OidcError::InvalidIdToken(_) => {
+                tracing::warn!("Rejected OIDC sign-in: {}", e);
+                AppError::InvalidToken
+            }
+        }
+    }
+}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(e: reqwest::Error) -> Self {
+        OidcError::Provider(e.to_string())
+    }
+}
+
+/// Answer of the token endpoint; only the ID token is used
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+}
+
+/// OpenID Connect relying party for the configured identity providers.
+///
+/// Discovery documents and signing keys are fetched on first use and cached;
+/// cloning is cheap and shares the cache.
+#[derive(Clone)]
+pub struct OidcClient {
+    http: reqwest::Client,
+    providers: Arc<HashMap<String, ProviderCache>>,
+}
+
+impl OidcClient {
+    pub fn new(providers: &[OidcProviderConfig]) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
+
+        let providers = providers
+            .iter()
+            .map(|config| (config.name.clone(), ProviderCache::new(config.clone())))
+            .collect();
+
+        Self {
+            http,
+            providers: Arc::new(providers),
+        }
+    }
+
+    /// Names of the configured providers, sorted
+    pub fn provider_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.providers.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Whether a provider with this name is configured
+    pub fn has_provider(&self, name: &str) -> bool {
+        self.providers.contains_key(name)
+    }
+
+    fn provider(&self, name: &str) -> Result<&ProviderCache, AppError> {
+        self.providers
+            .get(name)
+            .ok_or_else(|| AppError::NotFound(format!("Unknown identity provider '{}'", name)))
+    }
+
+    /// URL that starts the authorization code flow at the provider
+    pub async fn authorization_url(
+        &self,
+        provider: &str,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String, AppError> {
+        let provider = self.provider(provider)?;
+        let metadata = provider.metadata(&self.http).await?;
+        let config = &provider.config;
+
+        let mut url = Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| OidcError::Provider(format!("invalid authorization_endpoint: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", &config.redirect_uri)
+            .append_pair("scope", &config.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(url.into())
+    }
+
+    /// Redeem an authorization code and return the validated ID token claims
+    pub async fn exchange_code(
+        &self,
+        provider: &str,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let provider = self.provider(provider)?;
+        let metadata = provider.metadata(&self.http).await?;
+        let config = &provider.config;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &config.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(OidcError::from)?;
+
+        // A rejected code (expired, replayed, wrong verifier) is the client's problem
+        if response.status().is_client_error() {
+            let body = response.text().await.unwrap_or_default();
+            tracing::warn!("{} rejected authorization code: {}", config.name, body);
+            return Err(AppError::InvalidToken);
+        }
+
+        let tokens: TokenResponse = response
+            .error_for_status()
+            .map_err(OidcError::from)?
+            .json()
+            .await
+            .map_err(OidcError::from)?;
+        let id_token = tokens.id_token.ok_or_else(|| {
+            OidcError::Provider("token response did not include an id_token".to_string())
+        })?;
+
+        let claims = provider
+            .validate_id_token(&self.http, &id_token, nonce)
+            .await?;
+        Ok(claims)
+    }
+}
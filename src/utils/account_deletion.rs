@@ -0,0 +1,157 @@
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    errors::AppError,
+    utils::audit::{AuditEvent, record_audit_event},
+};
+
+/// Call off a scheduled deletion; returns whether one was pending
+pub async fn cancel_account_deletion(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<bool, AppError> {
+    let cancelled = sqlx::query!(
+        r#"
+        UPDATE users SET deletion_scheduled_at = NULL, updated_at = NOW()
+        WHERE id = $1 AND deletion_scheduled_at IS NOT NULL
+        "#,
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if cancelled {
+        record_audit_event(conn, user_id, AuditEvent::DeletionCancelled, json!({})).await?;
+        tracing::info!("Cancelled scheduled deletion of user {}", user_id);
+    }
+
+    Ok(cancelled)
+}
+
+/// Delete up to `batch_size` accounts whose grace period is over.
+///
+/// Each account is purged in its own transaction. Returns the number purged.
+pub async fn purge_due_accounts(db: &PgPool, batch_size: i64) -> Result<usize, AppError> {
+    let mut purged = 0;
+
+    for _ in 0..batch_size {
+        let mut tx = db.begin().await?;
+
+        // Skip rows another worker (or a concurrent login) holds
+        let Some(user_id) = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM users
+            WHERE deletion_scheduled_at <= NOW()
+            ORDER BY deletion_scheduled_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            break;
+        };
+
+        purge_account(&mut tx, user_id).await?;
+        tx.commit().await?;
+        purged += 1;
+    }
+
+    Ok(purged)
+}
+
+/// Remove an account and its personal data.
+///
+/// Active joints it created go to another member (moderators first, then the
+/// longest-standing member) or are closed when nobody else is left. Messages
+/// stay with `user_id` set to NULL. Run inside a transaction.
+async fn purge_account(conn: &mut PgConnection, user_id: Uuid) -> Result<(), AppError> {
+    let joints = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM joints
+        WHERE creator_id = $1 AND is_active = true AND expires_at > NOW()
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut transferred = 0;
+    let mut closed = 0;
+    for joint_id in joints {
+        let successor = sqlx::query_scalar!(
+            r#"
+            SELECT user_id FROM joint_members
+            WHERE joint_id = $1 AND user_id <> $2
+            ORDER BY (role = 'moderator') DESC, joined_at ASC
+            LIMIT 1
+            "#,
+            joint_id,
+            user_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        match successor {
+            Some(successor) => {
+                sqlx::query!(
+                    "UPDATE joints SET creator_id = $1 WHERE id = $2",
+                    successor,
+                    joint_id
+                )
+                .execute(&mut *conn)
+                .await?;
+                sqlx::query!(
+                    "UPDATE joint_members SET role = 'creator' WHERE joint_id = $1 AND user_id = $2",
+                    joint_id,
+                    successor
+                )
+                .execute(&mut *conn)
+                .await?;
+                transferred += 1;
+            }
+            None => {
+                sqlx::query!(
+                    "UPDATE joints SET is_active = false WHERE id = $1",
+                    joint_id
+                )
+                .execute(&mut *conn)
+                .await?;
+                closed += 1;
+            }
+        }
+    }
+
+    // Queued and sent email holds the address and rendered personal data
+    sqlx::query!("DELETE FROM email_outbox WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    record_audit_event(
+        conn,
+        user_id,
+        AuditEvent::AccountDeleted,
+        json!({ "joints_transferred": transferred, "joints_closed": closed }),
+    )
+    .await?;
+
+    // Memberships, tokens, codes and linked identities go with the row;
+    // messages and joints keep existing with their user reference cleared
+    sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    tracing::info!(
+        "Deleted user {} ({} joints transferred, {} closed)",
+        user_id,
+        transferred,
+        closed
+    );
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use serde_json::Value;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+/// Account events kept in the audit trail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEvent {
+    DeletionRequested,
+    DeletionCancelled,
+    AccountDeleted,
+}
+
+impl AuditEvent {
+    /// Value stored in `audit_events.event`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEvent::DeletionRequested => "deletion_requested",
+            AuditEvent::DeletionCancelled => "deletion_cancelled",
+            AuditEvent::AccountDeleted => "account_deleted",
+        }
+    }
+}
+
+/// Append an event to the audit trail; `details` must not contain personal data
+pub async fn record_audit_event(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    event: AuditEvent,
+    details: Value,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "INSERT INTO audit_events (user_id, event, details) VALUES ($1, $2, $3)",
+        user_id,
+        event.as_str(),
+        details
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
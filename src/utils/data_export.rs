@@ -0,0 +1,239 @@
+use std::io::{Cursor, Write};
+
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::{errors::AppError, utils::refresh_token::hash_token};
+
+/// Minimum delay between two export requests of the same user
+pub const EXPORT_COOLDOWN: Duration = Duration::hours(1);
+
+/// An export still processing after this long is assumed abandoned and retried
+const STALE_AFTER_MINUTES: i64 = 15;
+
+/// Generate a random opaque download token (256 bits, hex encoded)
+fn generate_download_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Queue a new export for a user; returns its id and download token.
+///
+/// Fails with `TooManyRequests` while an earlier export is still being built
+/// or was requested less than `EXPORT_COOLDOWN` ago.
+pub async fn create_data_export(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<(Uuid, String), AppError> {
+    // Serialize requests of the same user
+    sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let last_requested = sqlx::query_scalar!(
+        r#"
+        SELECT MAX(created_at) FROM data_exports
+        WHERE user_id = $1 AND status <> 'failed'
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if let Some(last_requested) = last_requested {
+        let wait = (last_requested + EXPORT_COOLDOWN - Utc::now()).num_seconds();
+        if wait > 0 {
+            return Err(AppError::TooManyRequests {
+                retry_after_secs: wait,
+            });
+        }
+    }
+
+    let token = generate_download_token();
+    let id = sqlx::query_scalar!(
+        "INSERT INTO data_exports (user_id, token_hash) VALUES ($1, $2) RETURNING id",
+        user_id,
+        hash_token(&token)
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok((id, token))
+}
+
+/// Build the oldest pending export, if any. Returns whether one was claimed.
+///
+/// The archive stays downloadable for `ttl_hours` after it is ready; failed
+/// exports are kept as long so their status can still be read.
+pub async fn process_next_export(db: &PgPool, ttl_hours: i64) -> Result<bool, AppError> {
+    // Claim in its own transaction so the row is not locked while building
+    let claimed = sqlx::query!(
+        r#"
+        UPDATE data_exports SET status = 'processing', started_at = NOW()
+        WHERE id = (
+            SELECT id FROM data_exports
+            WHERE status = 'pending'
+               OR (status = 'processing' AND started_at < NOW() - make_interval(mins => $1))
+            ORDER BY created_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, user_id
+        "#,
+        STALE_AFTER_MINUTES as i32
+    )
+    .fetch_optional(db)
+    .await?;
+
+    let Some(export) = claimed else {
+        return Ok(false);
+    };
+
+    match build_export(db, export.user_id).await {
+        Ok(archive) => {
+            sqlx::query!(
+                r#"
+                UPDATE data_exports
+                SET status = 'ready', archive = $1, completed_at = NOW(), expires_at = $2
+                WHERE id = $3
+                "#,
+                archive,
+                Utc::now() + Duration::hours(ttl_hours),
+                export.id
+            )
+            .execute(db)
+            .await?;
+
+            tracing::info!(
+                "Built data export {} for user {} ({} bytes)",
+                export.id,
+                export.user_id,
+                archive.len()
+            );
+        }
+        Err(e) => {
+            tracing::error!("Data export {} failed: {:?}", export.id, e);
+            sqlx::query!(
+                r#"
+                UPDATE data_exports
+                SET status = 'failed', error = $1, completed_at = NOW(), expires_at = $2
+                WHERE id = $3
+                "#,
+                format!("{:?}", e),
+                Utc::now() + Duration::hours(ttl_hours),
+                export.id
+            )
+            .execute(db)
+            .await?;
+        }
+    }
+
+    Ok(true)
+}
+
+/// Remove exports whose download window has closed
+pub async fn delete_expired_exports(db: &PgPool) -> Result<u64, AppError> {
+    let deleted = sqlx::query!("DELETE FROM data_exports WHERE expires_at < NOW()")
+        .execute(db)
+        .await?
+        .rows_affected();
+
+    Ok(deleted)
+}
+
+/// Collect the user's data and zip it
+async fn build_export(db: &PgPool, user_id: Uuid) -> Result<Vec<u8>, AppError> {
+    let mut conn = db.acquire().await?;
+    let files = collect_files(&mut conn, user_id).await?;
+
+    tokio::task::spawn_blocking(move || build_archive(&files))
+        .await
+        .map_err(|e| AppError::InternalError(e.to_string()))?
+}
+
+/// One JSON document per file of the archive.
+///
+/// Rows are converted with `to_jsonb`, so new columns show up without changes
+/// here. Credentials (password hash, TOTP secret) are left out.
+async fn collect_files(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<(&'static str, Value)>, AppError> {
+    let user = sqlx::query_scalar!(
+        r#"
+        SELECT to_jsonb(u) - 'password_hash' - 'totp_secret' - 'totp_last_step' as "user!"
+        FROM users u WHERE id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let memberships = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(
+            jsonb_agg(to_jsonb(jm) || jsonb_build_object('joint_name', j.name) ORDER BY jm.joined_at),
+            '[]'
+        ) as "memberships!"
+        FROM joint_members jm
+        JOIN joints j ON j.id = jm.joint_id
+        WHERE jm.user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let joints = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(jsonb_agg(to_jsonb(j) ORDER BY j.created_at), '[]') as "joints!"
+        FROM joints j
+        WHERE j.creator_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let messages = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(jsonb_agg(to_jsonb(m) ORDER BY m.created_at), '[]') as "messages!"
+        FROM messages m
+        WHERE m.user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(vec![
+        ("user.json", user),
+        ("joint_memberships.json", memberships),
+        ("joints_created.json", joints),
+        ("messages.json", messages),
+    ])
+}
+
+/// Write the files into an in-memory zip archive
+fn build_archive(files: &[(&'static str, Value)]) -> Result<Vec<u8>, AppError> {
+    let archive_error = |e: &dyn std::fmt::Display| {
+        AppError::InternalError(format!("Failed to build export archive: {}", e))
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (name, value) in files {
+        zip.start_file(*name, options)
+            .map_err(|e| archive_error(&e))?;
+        let json = serde_json::to_vec_pretty(value).map_err(|e| archive_error(&e))?;
+        zip.write_all(&json).map_err(|e| archive_error(&e))?;
+    }
+
+    Ok(zip.finish().map_err(|e| archive_error(&e))?.into_inner())
+}
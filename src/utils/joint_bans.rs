@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+/// Fail with `Forbidden` while the user has an active ban from the joint
+pub async fn ensure_not_banned(
+    conn: &mut PgConnection,
+    joint_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    let ban = sqlx::query!(
+        r#"
+        SELECT reason, expires_at FROM joint_bans
+        WHERE joint_id = $1 AND user_id = $2 AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+        joint_id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    let Some(ban) = ban else {
+        return Ok(());
+    };
+
+    Err(AppError::Forbidden(ban_message(
+        ban.expires_at,
+        ban.reason.as_deref(),
+    )))
+}
+
+fn ban_message(expires_at: Option<DateTime<Utc>>, reason: Option<&str>) -> String {
+    let mut message = match expires_at {
+        Some(expires_at) => format!(
+            "You are banned from this joint until {}",
+            expires_at.to_rfc3339()
+        ),
+        None => "You are banned from this joint".to_string(),
+    };
+    if let Some(reason) = reason {
+        message.push_str(&format!(" ({})", reason));
+    }
+    message
+}
@@ -0,0 +1,61 @@
+use crate::{errors::AppError, models::joint::JointRole};
+
+/// Actions in a joint that depend on the member's role
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JointAction {
+    /// Change name, description or radius
+    EditJoint,
+    CloseJoint,
+    DeleteJoint,
+    /// Hand the creator role to another member
+    TransferOwnership,
+    PromoteModerator,
+    DemoteModerator,
+    /// Remove a member, who may join again
+    KickMember,
+    /// Remove a member and keep them out
+    BanMember,
+    UnbanMember,
+}
+
+/// The permission matrix: whether `role` may perform `action`
+pub fn allows(role: JointRole, action: JointAction) -> bool {
+    use JointAction::*;
+
+    match role {
+        JointRole::Creator => true,
+        JointRole::Moderator => matches!(action, EditJoint | KickMember | BanMember | UnbanMember),
+        JointRole::Member => false,
+    }
+}
+
+/// Fail with `Forbidden` unless `role` may perform `action`; None means not a member.
+///
+/// Returns the role that was authorized.
+pub fn authorize(role: Option<JointRole>, action: JointAction) -> Result<JointRole, AppError> {
+    match role {
+        Some(role) if allows(role, action) => Ok(role),
+        Some(role) => Err(AppError::Forbidden(format!(
+            "A joint {} is not allowed to do this",
+            role.as_str()
+        ))),
+        None => Err(AppError::Forbidden(
+            "You are not a member of this joint".to_string(),
+        )),
+    }
+}
+
+/// Whether a member with role `actor` may kick or ban one with role `target`:
+/// only members ranked strictly lower, so moderators cannot remove each other
+/// and nobody can remove the creator
+pub fn outranks(actor: JointRole, target: JointRole) -> bool {
+    fn rank(role: JointRole) -> u8 {
+        match role {
+            JointRole::Creator => 2,
+            JointRole::Moderator => 1,
+            JointRole::Member => 0,
+        }
+    }
+
+    rank(actor) > rank(target)
+}
@@ -1,5 +1,15 @@
+use std::collections::HashMap;
+
+use aws_lc_rs::signature::{Ed25519KeyPair, KeyPair};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, ThumbprintHash,
+};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, decode_header, encode,
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -11,38 +21,196 @@ pub struct Claims {
     pub sub: String, // Subject (user_id)
     pub exp: i64,    // Expiration time
     pub iat: i64,    // Issued at
+    pub jti: Uuid,   // Token ID (used for revocation)
+}
+
+/// A key that access tokens are checked against
+#[derive(Clone)]
+struct VerificationKey {
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// Keys used to sign access tokens and to verify them.
+///
+/// Tokens are signed with the current key and carry its `kid`; verification
+/// also accepts the previous keys listed in the configuration, so a key can
+/// be rotated without logging everyone out. Tokens without a `kid` are
+/// checked against the current key.
+#[derive(Clone)]
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    key_id: Option<String>,
+    encoding_key: EncodingKey,
+    current: VerificationKey,
+    previous: HashMap<String, VerificationKey>,
+    jwks: JwkSet,
+}
+
+impl JwtKeys {
+    /// HS256 with a shared secret; nothing is published in the JWKS
+    pub fn hmac(secret: &str) -> Self {
+        Self {
+            algorithm: Algorithm::HS256,
+            key_id: None,
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            current: VerificationKey {
+                algorithm: Algorithm::HS256,
+                key: DecodingKey::from_secret(secret.as_bytes()),
+            },
+            previous: HashMap::new(),
+            jwks: JwkSet { keys: Vec::new() },
+        }
+    }
+
+    /// RS256 or EdDSA with a PEM private key.
+    ///
+    /// `key_id` defaults to the RFC 7638 thumbprint of the public key;
+    /// `previous_jwks` is a JWK Set of retired public keys still accepted.
+    pub fn from_pem(
+        algorithm: Algorithm,
+        private_key_pem: &str,
+        key_id: Option<String>,
+        previous_jwks: Option<&str>,
+    ) -> Result<Self, String> {
+        let (encoding_key, mut jwk) = match algorithm {
+            Algorithm::RS256 => {
+                let encoding_key = EncodingKey::from_rsa_pem(private_key_pem.as_bytes())
+                    .map_err(|e| format!("invalid RSA private key: {}", e))?;
+                let jwk = Jwk::from_encoding_key(&encoding_key, algorithm)
+                    .map_err(|e| format!("invalid RSA private key: {}", e))?;
+                (encoding_key, jwk)
+            }
+            Algorithm::EdDSA => ed25519_keys(private_key_pem)?,
+            other => return Err(format!("unsupported algorithm {:?}", other)),
+        };
+
+        let key_id = key_id.unwrap_or_else(|| jwk.thumbprint(ThumbprintHash::SHA256));
+        jwk.common.key_id = Some(key_id.clone());
+        jwk.common.public_key_use = Some(PublicKeyUse::Signature);
+
+        let current = VerificationKey {
+            algorithm,
+            key: DecodingKey::from_jwk(&jwk).map_err(|e| format!("invalid public key: {}", e))?,
+        };
+
+        let mut jwks = JwkSet { keys: vec![jwk] };
+        let mut previous = HashMap::new();
+
+        if let Some(previous_jwks) = previous_jwks {
+            let set: JwkSet = serde_json::from_str(previous_jwks)
+                .map_err(|e| format!("invalid previous keys: {}", e))?;
+
+            for jwk in set.keys {
+                let kid = jwk
+                    .common
+                    .key_id
+                    .clone()
+                    .ok_or("every previous key needs a kid")?;
+                let algorithm = jwk
+                    .common
+                    .key_algorithm
+                    .and_then(|alg| alg.to_string().parse::<Algorithm>().ok())
+                    .ok_or_else(|| format!("previous key {} needs a supported alg", kid))?;
+                if matches!(
+                    algorithm,
+                    Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+                ) {
+                    return Err(format!("previous key {} must be a public key", kid));
+                }
+                if kid == key_id {
+                    continue;
+                }
+
+                let key = DecodingKey::from_jwk(&jwk)
+                    .map_err(|e| format!("invalid previous key {}: {}", kid, e))?;
+                previous.insert(kid, VerificationKey { algorithm, key });
+                jwks.keys.push(jwk);
+            }
+        }
+
+        Ok(Self {
+            algorithm,
+            key_id: Some(key_id),
+            encoding_key,
+            current,
+            previous,
+            jwks,
+        })
+    }
+
+    /// Public keys accepted for verification, for `/.well-known/jwks.json`
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+
+    /// Pick the verification key named by the token's `kid`
+    fn verification_key(&self, kid: Option<&str>) -> Option<&VerificationKey> {
+        match kid {
+            None => Some(&self.current),
+            Some(kid) if self.key_id.as_deref() == Some(kid) => Some(&self.current),
+            Some(kid) => self.previous.get(kid),
+        }
+    }
+}
+
+/// Load an Ed25519 PKCS#8 private key and derive its public JWK
+fn ed25519_keys(private_key_pem: &str) -> Result<(EncodingKey, Jwk), String> {
+    let der = pem::parse(private_key_pem)
+        .map_err(|e| format!("invalid Ed25519 private key: {}", e))?
+        .into_contents();
+    let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(&der)
+        .map_err(|e| format!("invalid Ed25519 private key: {}", e))?;
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            key_algorithm: Some(KeyAlgorithm::EdDSA),
+            ..Default::default()
+        },
+        algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+            key_type: OctetKeyPairType::OctetKeyPair,
+            curve: EllipticCurve::Ed25519,
+            x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+        }),
+    };
+
+    Ok((EncodingKey::from_ed_der(&der), jwk))
 }
 
 /// Generate a JWT token for a user
-pub fn generate_token(user_id: Uuid, secret: &str, expiry_hours: i64) -> Result<String, AppError> {
+pub fn generate_token(user_id: Uuid, keys: &JwtKeys, expiry_secs: i64) -> Result<String, AppError> {
     let now = Utc::now();
-    let expires_at = now + Duration::hours(expiry_hours);
+    let expires_at = now + Duration::seconds(expiry_secs);
 
     let claims = Claims {
         sub: user_id.to_string(),
         exp: expires_at.timestamp(),
         iat: now.timestamp(),
+        jti: Uuid::new_v4(),
     };
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
-    .map_err(|e| AppError::InternalError(format!("Failed to generate token: {}", e)))
+    let mut header = Header::new(keys.algorithm);
+    header.kid = keys.key_id.clone();
+
+    encode(&header, &claims, &keys.encoding_key)
+        .map_err(|e| AppError::InternalError(format!("Failed to generate token: {}", e)))
 }
 
 /// Verify and decode a JWT token
-pub fn verify_token(token: &str, secret: &str) -> Result<Claims, AppError> {
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
-    )
-    .map_err(|e| match e.kind() {
-        jsonwebtoken::errors::ErrorKind::ExpiredSignature => AppError::TokenExpired,
-        _ => AppError::InvalidToken,
-    })?;
+pub fn verify_token(token: &str, keys: &JwtKeys) -> Result<Claims, AppError> {
+    let header = decode_header(token).map_err(|_| AppError::InvalidToken)?;
+    let key = keys
+        .verification_key(header.kid.as_deref())
+        .ok_or(AppError::InvalidToken)?;
+
+    // The algorithm is pinned by the key, never taken from the token
+    let token_data =
+        decode::<Claims>(token, &key.key, &Validation::new(key.algorithm)).map_err(|e| match e
+            .kind()
+        {
+            jsonwebtoken::errors::ErrorKind::ExpiredSignature => AppError::TokenExpired,
+            _ => AppError::InvalidToken,
+        })?;
 
     Ok(token_data.claims)
 }
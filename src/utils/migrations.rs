@@ -0,0 +1,106 @@
+use std::fmt;
+
+use sqlx::PgPool;
+use sqlx::migrate::{MigrateError, Migrator};
+
+/// Migrations from `migrations/`, embedded at compile time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Error raised while checking or applying the database schema at startup
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The database was migrated by a newer build than this one
+    SchemaTooNew {
+        database: i64,
+        binary: i64,
+    },
+    Database(sqlx::Error),
+    Migrate(MigrateError),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::SchemaTooNew { database, binary } => write!(
+                f,
+                "database schema version {} is newer than the latest migration known to this build ({})",
+                database, binary
+            ),
+            MigrationError::Database(e) => write!(f, "database error: {}", e),
+            MigrationError::Migrate(e) => write!(f, "migration failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<sqlx::Error> for MigrationError {
+    fn from(e: sqlx::Error) -> Self {
+        MigrationError::Database(e)
+    }
+}
+
+/// Latest migration version embedded in this binary
+pub fn latest_known_version() -> Option<i64> {
+    MIGRATOR.iter().map(|migration| migration.version).max()
+}
+
+/// Versions recorded as applied; empty when the database was never migrated
+async fn applied_versions(db: &PgPool) -> Result<Vec<i64>, sqlx::Error> {
+    let has_table: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(db)
+        .await?;
+
+    if !has_table {
+        return Ok(Vec::new());
+    }
+
+    sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success ORDER BY version")
+        .fetch_all(db)
+        .await
+}
+
+/// Current schema version, i.e. the newest successfully applied migration
+pub async fn schema_version(db: &PgPool) -> Result<Option<i64>, sqlx::Error> {
+    Ok(applied_versions(db).await?.into_iter().max())
+}
+
+/// Refuse to run against a schema migrated by a newer build, whose code
+/// this binary may not be compatible with
+pub async fn ensure_schema_compatible(db: &PgPool) -> Result<(), MigrationError> {
+    let binary = latest_known_version().unwrap_or(0);
+
+    match schema_version(db).await? {
+        Some(database) if database > binary => {
+            Err(MigrationError::SchemaTooNew { database, binary })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Apply pending migrations and log the ones that ran
+pub async fn run_migrations(db: &PgPool) -> Result<(), MigrationError> {
+    ensure_schema_compatible(db).await?;
+
+    let before = applied_versions(db).await?;
+    MIGRATOR.run(db).await.map_err(MigrationError::Migrate)?;
+
+    let mut applied = 0;
+    for migration in MIGRATOR.iter() {
+        if !migration.migration_type.is_down_migration() && !before.contains(&migration.version) {
+            tracing::info!(
+                "Applied migration {} ({})",
+                migration.version,
+                migration.description
+            );
+            applied += 1;
+        }
+    }
+
+    tracing::info!(
+        "Database schema at version {} ({} migrations applied)",
+        latest_known_version().unwrap_or(0),
+        applied
+    );
+    Ok(())
+}
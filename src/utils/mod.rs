@@ -1,3 +1,17 @@
+pub mod account_deletion;
+pub mod audit;
+pub mod data_export;
 pub mod email;
+pub mod joint_bans;
+pub mod joint_permissions;
 pub mod jwt;
+pub mod migrations;
+pub mod normalize;
+pub mod otp;
 pub mod password;
+pub mod password_policy;
+pub mod refresh_token;
+pub mod revocation;
+pub mod throttle;
+pub mod totp;
+pub mod two_factor;
@@ -0,0 +1,14 @@
+/// Canonical form of an email address: trimmed and lowercased
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Usernames keep their case for display but are compared case-insensitively
+pub fn normalize_username(username: &str) -> String {
+    username.trim().to_string()
+}
+
+/// Whether a login identifier is an email address rather than a username
+pub fn is_email_identifier(identifier: &str) -> bool {
+    identifier.contains('@')
+}
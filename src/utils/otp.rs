@@ -0,0 +1,195 @@
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sqlx::{PgConnection, PgPool};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+/// What an OTP code may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpPurpose {
+    Verification,
+    PasswordReset,
+    EmailChange,
+    Login,
+}
+
+impl OtpPurpose {
+    /// Value stored in `otp_codes.purpose`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OtpPurpose::Verification => "verification",
+            OtpPurpose::PasswordReset => "password_reset",
+            OtpPurpose::EmailChange => "email_change",
+            OtpPurpose::Login => "login",
+        }
+    }
+}
+
+/// Generate a 6-digit OTP code
+pub fn generate_otp_code() -> String {
+    rand::rng().random_range(100000..999999).to_string()
+}
+
+/// Create and store a new OTP code for a user
+pub async fn create_otp(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    purpose: OtpPurpose,
+    ttl_minutes: i64,
+) -> Result<String, AppError> {
+    let code = generate_otp_code();
+    let expires_at = Utc::now() + Duration::minutes(ttl_minutes);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO otp_codes (user_id, code, expires_at, purpose)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        code,
+        expires_at,
+        purpose.as_str()
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(code)
+}
+
+/// Enforce the resend cooldown and daily cap before issuing another code
+pub async fn check_otp_send_limit(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    purpose: OtpPurpose,
+    cooldown_secs: i64,
+    daily_limit: i64,
+) -> Result<(), AppError> {
+    let stats = sqlx::query!(
+        r#"
+        SELECT
+            MIN(created_at) as first_sent,
+            MAX(created_at) as last_sent,
+            COUNT(*) as "sent!"
+        FROM otp_codes
+        WHERE user_id = $1 AND purpose = $2 AND created_at > NOW() - INTERVAL '24 hours'
+        "#,
+        user_id,
+        purpose.as_str()
+    )
+    .fetch_one(conn)
+    .await?;
+
+    let now = Utc::now();
+
+    if let Some(last_sent) = stats.last_sent {
+        let available_at = last_sent + Duration::seconds(cooldown_secs);
+        if available_at > now {
+            return Err(AppError::TooManyRequests {
+                retry_after_secs: (available_at - now).num_seconds().max(1),
+            });
+        }
+    }
+
+    if stats.sent >= daily_limit
+        && let Some(first_sent) = stats.first_sent
+    {
+        let available_at = first_sent + Duration::hours(24);
+        return Err(AppError::TooManyRequests {
+            retry_after_secs: (available_at - now).num_seconds().max(1),
+        });
+    }
+
+    Ok(())
+}
+
+/// Invalidate all unused codes of a user for a purpose
+pub async fn invalidate_otps(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    purpose: OtpPurpose,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE otp_codes SET is_used = true WHERE user_id = $1 AND purpose = $2 AND is_used = false",
+        user_id,
+        purpose.as_str()
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Check an OTP code and mark it as used.
+///
+/// Only the newest unused code of the purpose is accepted. Every wrong guess
+/// is counted on that code and it is locked after `max_attempts` failures,
+/// so the user has to request a new one.
+pub async fn consume_otp(
+    db: &PgPool,
+    user_id: Uuid,
+    code: &str,
+    purpose: OtpPurpose,
+    max_attempts: i32,
+) -> Result<(), AppError> {
+    let mut tx = db.begin().await?;
+
+    // Find the active OTP (row lock serializes concurrent guesses)
+    let otp = sqlx::query!(
+        r#"
+        SELECT id, code, expires_at, failed_attempts
+        FROM otp_codes
+        WHERE user_id = $1 AND purpose = $2 AND is_used = false
+        ORDER BY created_at DESC
+        LIMIT 1
+        FOR UPDATE
+        "#,
+        user_id,
+        purpose.as_str()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::InvalidOtp)?;
+
+    // Check if OTP expired
+    if otp.expires_at < Utc::now() {
+        return Err(AppError::OtpExpired);
+    }
+
+    if otp.failed_attempts >= max_attempts {
+        return Err(AppError::OtpLocked);
+    }
+
+    if !bool::from(otp.code.as_bytes().ct_eq(code.as_bytes())) {
+        // Count the failure; burn the code once the limit is reached
+        let failed_attempts = otp.failed_attempts + 1;
+        sqlx::query!(
+            r#"
+            UPDATE otp_codes
+            SET failed_attempts = failed_attempts + 1, is_used = (failed_attempts + 1 >= $1)
+            WHERE id = $2
+            "#,
+            max_attempts,
+            otp.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::warn!("Wrong {} code for user {}", purpose.as_str(), user_id);
+        return Err(if failed_attempts >= max_attempts {
+            AppError::OtpLocked
+        } else {
+            AppError::InvalidOtp
+        });
+    }
+
+    // Mark OTP as used
+    sqlx::query!("UPDATE otp_codes SET is_used = true WHERE id = $1", otp.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
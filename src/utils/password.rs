@@ -1,27 +1,66 @@
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, Params, Version,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 
 use crate::errors::AppError;
 
-/// Hash a password using Argon2
-pub fn hash_password(password: &str) -> Result<String, AppError> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
+/// Hash a password using Argon2id with the given cost parameters.
+///
+/// Argon2 is deliberately slow, so the work runs on a blocking thread.
+pub async fn hash_password(password: &str, params: &Params) -> Result<String, AppError> {
+    let password = password.to_owned();
+    let params = params.clone();
 
-    argon2
-        .hash_password(password.as_bytes(), &salt)
-        .map(|hash| hash.to_string())
-        .map_err(|e| AppError::InternalError(format!("Failed to hash password: {}", e)))
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+        argon2
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| AppError::InternalError(format!("Failed to hash password: {}", e)))
+    })
+    .await
+    .map_err(|e| AppError::InternalError(format!("Password hashing task failed: {}", e)))?
+}
+
+/// Verify a password against a hash, using the parameters stored in the hash
+pub async fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
+    let password = password.to_owned();
+    let hash = hash.to_owned();
+
+    tokio::task::spawn_blocking(move || {
+        let parsed_hash = PasswordHash::new(&hash)
+            .map_err(|e| AppError::InternalError(format!("Invalid password hash: {}", e)))?;
+
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok())
+    })
+    .await
+    .map_err(|e| AppError::InternalError(format!("Password verification task failed: {}", e)))?
 }
 
-/// Verify a password against a hash
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
-    let parsed_hash = PasswordHash::new(hash)
-        .map_err(|e| AppError::InternalError(format!("Invalid password hash: {}", e)))?;
+/// Whether a stored hash should be replaced: it uses another algorithm or
+/// version, or any cost parameter is weaker than the current settings
+pub fn needs_rehash(hash: &str, params: &Params) -> bool {
+    let Ok(parsed_hash) = PasswordHash::new(hash) else {
+        return true;
+    };
+
+    if parsed_hash.algorithm != Algorithm::Argon2id.ident()
+        || parsed_hash.version != Some(Version::V0x13.into())
+    {
+        return true;
+    }
 
-    Ok(Argon2::default()
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .is_ok())
+    match Params::try_from(&parsed_hash) {
+        Ok(stored) => {
+            stored.m_cost() < params.m_cost()
+                || stored.t_cost() < params.t_cost()
+                || stored.p_cost() < params.p_cost()
+        }
+        Err(_) => true,
+    }
 }
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use crate::errors::AppError;
+
+/// Common and breached passwords, bundled from `data/common_passwords.txt`
+static COMMON_PASSWORDS: LazyLock<HashSet<String>> = LazyLock::new(|| {
+    include_str!("../../data/common_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+});
+
+/// Personal values shorter than this are not checked as substrings;
+/// they would reject too many unrelated passwords
+const MIN_PERSONAL_INFO_LENGTH: usize = 3;
+
+/// Rules a new password has to satisfy
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// Minimum length in characters
+    pub min_length: usize,
+    /// Maximum length in characters, bounding the hashing cost
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    /// Require a character that is neither a letter nor a digit
+    pub require_symbol: bool,
+    /// Reject passwords on the bundled common/breached list
+    pub reject_common: bool,
+}
+
+impl PasswordPolicy {
+    /// Check a new password, listing every rule it breaks.
+    ///
+    /// `personal_info` holds values the password must not contain, such as
+    /// the username and email address of the account.
+    pub fn check(&self, password: &str, personal_info: &[&str]) -> Result<(), AppError> {
+        let mut problems = Vec::new();
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            problems.push(format!(
+                "must be at least {} characters long",
+                self.min_length
+            ));
+        }
+        if length > self.max_length {
+            problems.push(format!(
+                "must be at most {} characters long",
+                self.max_length
+            ));
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            problems.push("must contain a lowercase letter".to_string());
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            problems.push("must contain an uppercase letter".to_string());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            problems.push("must contain a digit".to_string());
+        }
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            problems.push("must contain a symbol".to_string());
+        }
+        if contains_personal_info(password, personal_info) {
+            problems.push("must not contain your username or email address".to_string());
+        }
+        if self.reject_common && is_common_password(password) {
+            problems.push("is too common, choose a less guessable one".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::ValidationError(format!(
+                "Password {}",
+                problems.join(", ")
+            )))
+        }
+    }
+}
+
+/// Whether the password, or the password without trailing digits and
+/// symbols ("Dragon2024!" -> "dragon"), is on the bundled list
+fn is_common_password(password: &str) -> bool {
+    let lowered = password.to_lowercase();
+    let stem = lowered.trim_end_matches(|c: char| !c.is_alphabetic());
+
+    COMMON_PASSWORDS.contains(&lowered) || (!stem.is_empty() && COMMON_PASSWORDS.contains(stem))
+}
+
+/// Case-insensitive substring check against each personal value; for email
+/// addresses the local part is checked as well
+fn contains_personal_info(password: &str, personal_info: &[&str]) -> bool {
+    let lowered = password.to_lowercase();
+
+    personal_info
+        .iter()
+        .flat_map(|value| [*value, value.split('@').next().unwrap_or(value)])
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| value.chars().count() >= MIN_PERSONAL_INFO_LENGTH)
+        .any(|value| lowered.contains(&value))
+}
@@ -0,0 +1,161 @@
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::errors::AppError;
+
+/// A refresh token that was successfully exchanged for a new one
+pub struct RotatedToken {
+    pub user_id: Uuid,
+    pub refresh_token: String,
+}
+
+/// Generate a random opaque refresh token (256 bits, hex encoded)
+fn generate_refresh_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hash an opaque token for storage and lookup
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Issue a new refresh token, starting a new family when `family_id` is None
+pub async fn create_refresh_token(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    family_id: Option<Uuid>,
+    ttl_days: i64,
+) -> Result<String, AppError> {
+    let token = generate_refresh_token();
+    let expires_at = Utc::now() + Duration::days(ttl_days);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        family_id.unwrap_or_else(Uuid::new_v4),
+        hash_token(&token),
+        expires_at
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(token)
+}
+
+/// Exchange a refresh token for a new one in the same family.
+///
+/// Presenting a token that was already used or revoked is treated as theft:
+/// the whole family is revoked and the request is rejected.
+pub async fn rotate_refresh_token(
+    db: &PgPool,
+    token: &str,
+    ttl_days: i64,
+) -> Result<RotatedToken, AppError> {
+    let mut tx = db.begin().await?;
+
+    let stored = sqlx::query!(
+        r#"
+        SELECT id, user_id, family_id, expires_at, used_at, revoked_at
+        FROM refresh_tokens
+        WHERE token_hash = $1
+        FOR UPDATE
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::InvalidToken)?;
+
+    // Reuse detection: revoke every token descended from the same login
+    if stored.used_at.is_some() || stored.revoked_at.is_some() {
+        sqlx::query!(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+            stored.family_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::warn!(
+            "Refresh token reuse detected for user {}, revoked family {}",
+            stored.user_id,
+            stored.family_id
+        );
+        return Err(AppError::InvalidToken);
+    }
+
+    if stored.expires_at < Utc::now() {
+        return Err(AppError::TokenExpired);
+    }
+
+    // Mark the presented token as used and issue its successor
+    sqlx::query!(
+        "UPDATE refresh_tokens SET used_at = NOW() WHERE id = $1",
+        stored.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let refresh_token =
+        create_refresh_token(&mut tx, stored.user_id, Some(stored.family_id), ttl_days).await?;
+
+    tx.commit().await?;
+
+    Ok(RotatedToken {
+        user_id: stored.user_id,
+        refresh_token,
+    })
+}
+
+/// Revoke the family a refresh token belongs to (logout of one session)
+pub async fn revoke_refresh_family(
+    db: &PgPool,
+    user_id: Uuid,
+    token: &str,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE refresh_tokens SET revoked_at = NOW()
+        WHERE revoked_at IS NULL
+        AND family_id = (
+            SELECT family_id FROM refresh_tokens WHERE token_hash = $1 AND user_id = $2
+        )
+        "#,
+        hash_token(token),
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Revoke every refresh token of a user (logout everywhere)
+pub async fn revoke_all_refresh_tokens(db: &PgPool, user_id: Uuid) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove refresh tokens past their expiry; they can no longer be exchanged
+pub async fn delete_expired_refresh_tokens(db: &PgPool) -> Result<u64, AppError> {
+    let deleted = sqlx::query!("DELETE FROM refresh_tokens WHERE expires_at < NOW()")
+        .execute(db)
+        .await?
+        .rows_affected();
+
+    Ok(deleted)
+}
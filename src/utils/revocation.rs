@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{errors::AppError, utils::jwt::Claims};
+
+/// Prune expired cache entries once the cache grows past this size
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Cached revocation state of a single access token
+struct CacheEntry {
+    user_id: Uuid,
+    revoked: bool,
+    checked_at: Instant,
+    expires_at: i64,
+}
+
+/// Access token revocation check backed by the database.
+///
+/// Revoked tokens are cached until they expire; tokens found valid are
+/// re-checked against the database once `ttl` has passed, so revocations
+/// made by other instances take effect within that window.
+#[derive(Clone)]
+pub struct RevocationCache {
+    // Map: jti -> cached state
+    entries: Arc<RwLock<HashMap<Uuid, CacheEntry>>>,
+    ttl: Duration,
+}
+
+impl RevocationCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// Check whether a token has been revoked, individually or by a logout-all
+    pub async fn is_revoked(
+        &self,
+        db: &PgPool,
+        user_id: Uuid,
+        claims: &Claims,
+    ) -> Result<bool, AppError> {
+        if let Some(entry) = self.entries.read().await.get(&claims.jti)
+            && (entry.revoked || entry.checked_at.elapsed() < self.ttl)
+        {
+            return Ok(entry.revoked);
+        }
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                tokens_revoked_before,
+                EXISTS(SELECT 1 FROM revoked_tokens WHERE jti = $2) as "revoked!"
+            FROM users
+            WHERE id = $1
+            "#,
+            user_id,
+            claims.jti
+        )
+        .fetch_optional(db)
+        .await?;
+
+        // Tokens of deleted users are treated as revoked
+        let revoked = match row {
+            Some(row) => {
+                row.revoked || issued_before(claims.iat, row.tokens_revoked_before.as_ref())
+            }
+            None => true,
+        };
+
+        self.store(user_id, claims, revoked).await;
+        Ok(revoked)
+    }
+
+    /// Revoke a single access token
+    pub async fn revoke(
+        &self,
+        db: &PgPool,
+        user_id: Uuid,
+        claims: &Claims,
+    ) -> Result<(), AppError> {
+        let expires_at = DateTime::from_timestamp(claims.exp, 0).unwrap_or_else(Utc::now);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO revoked_tokens (jti, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+            claims.jti,
+            user_id,
+            expires_at
+        )
+        .execute(db)
+        .await?;
+
+        self.store(user_id, claims, true).await;
+        Ok(())
+    }
+
+    /// Revoke every access token issued to a user up to now
+    pub async fn revoke_all(&self, db: &PgPool, user_id: Uuid) -> Result<(), AppError> {
+        sqlx::query!(
+            "UPDATE users SET tokens_revoked_before = NOW() WHERE id = $1",
+            user_id
+        )
+        .execute(db)
+        .await?;
+
+        // Force a database re-check for every cached token of this user
+        self.entries
+            .write()
+            .await
+            .retain(|_, entry| entry.user_id != user_id || entry.revoked);
+        Ok(())
+    }
+
+    async fn store(&self, user_id: Uuid, claims: &Claims, revoked: bool) {
+        let mut entries = self.entries.write().await;
+
+        if entries.len() >= PRUNE_THRESHOLD {
+            let now = Utc::now().timestamp();
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+
+        entries.insert(
+            claims.jti,
+            CacheEntry {
+                user_id,
+                revoked,
+                checked_at: Instant::now(),
+                expires_at: claims.exp,
+            },
+        );
+    }
+}
+
+/// Remove revocations of access tokens that have expired anyway
+pub async fn delete_expired_revocations(db: &PgPool) -> Result<u64, AppError> {
+    let deleted = sqlx::query!("DELETE FROM revoked_tokens WHERE expires_at < NOW()")
+        .execute(db)
+        .await?
+        .rows_affected();
+
+    Ok(deleted)
+}
+
+/// Whether a token issued at `iat` predates the user's revocation cutoff.
+///
+/// `iat` has second precision, so tokens issued within the same second as
+/// the cutoff are kept; this lets a new session start right after logout-all.
+fn issued_before(iat: i64, cutoff: Option<&DateTime<Utc>>) -> bool {
+    cutoff.is_some_and(|cutoff| iat < cutoff.timestamp())
+}
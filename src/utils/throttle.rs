@@ -0,0 +1,117 @@
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+
+/// When to lock a throttle key and for how long
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutPolicy {
+    /// Failures allowed before the key is locked
+    pub max_failures: i32,
+    /// First lockout duration in seconds
+    pub base_secs: i64,
+    /// Upper bound for the lockout duration in seconds
+    pub max_secs: i64,
+}
+
+impl LockoutPolicy {
+    /// Lockout for the given failure count: doubles with every failure past the limit
+    fn lockout_secs(&self, failures: i32) -> Option<i64> {
+        if failures < self.max_failures {
+            return None;
+        }
+
+        let doublings = (failures - self.max_failures).min(20) as u32;
+        Some(
+            self.base_secs
+                .saturating_mul(1 << doublings)
+                .min(self.max_secs),
+        )
+    }
+}
+
+/// Fail with `TooManyRequests` while a key is locked
+pub async fn check_throttle(db: &PgPool, key: &str) -> Result<(), AppError> {
+    let locked_until = sqlx::query_scalar!(
+        "SELECT locked_until FROM auth_throttles WHERE key = $1",
+        key
+    )
+    .fetch_optional(db)
+    .await?
+    .flatten();
+
+    let now = Utc::now();
+    match locked_until {
+        Some(until) if until > now => Err(AppError::TooManyRequests {
+            retry_after_secs: (until - now).num_seconds().max(1),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Count a failed attempt and lock the key once the policy limit is reached.
+///
+/// Counters restart after a day without failures.
+pub async fn record_failure(db: &PgPool, key: &str, policy: LockoutPolicy) -> Result<(), AppError> {
+    let failures = sqlx::query_scalar!(
+        r#"
+        INSERT INTO auth_throttles (key, failures, updated_at)
+        VALUES ($1, 1, NOW())
+        ON CONFLICT (key) DO UPDATE SET
+            failures = CASE
+                WHEN auth_throttles.updated_at < NOW() - INTERVAL '24 hours' THEN 1
+                ELSE auth_throttles.failures + 1
+            END,
+            updated_at = NOW()
+        RETURNING failures
+        "#,
+        key
+    )
+    .fetch_one(db)
+    .await?;
+
+    if let Some(secs) = policy.lockout_secs(failures) {
+        let locked_until = Utc::now() + Duration::seconds(secs);
+        sqlx::query!(
+            "UPDATE auth_throttles SET locked_until = $1 WHERE key = $2",
+            locked_until,
+            key
+        )
+        .execute(db)
+        .await?;
+
+        tracing::warn!(
+            "Throttling {} for {} seconds after {} failures",
+            key,
+            secs,
+            failures
+        );
+    }
+
+    Ok(())
+}
+
+/// Remove counters that would restart anyway and are not locked
+pub async fn delete_stale_throttles(db: &PgPool) -> Result<u64, AppError> {
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM auth_throttles
+        WHERE updated_at < NOW() - INTERVAL '24 hours'
+          AND (locked_until IS NULL OR locked_until < NOW())
+        "#
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(deleted)
+}
+
+/// Reset a key after a successful attempt
+pub async fn clear_throttle(db: &PgPool, key: &str) -> Result<(), AppError> {
+    sqlx::query!("DELETE FROM auth_throttles WHERE key = $1", key)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use chrono::{Duration, Utc};
+use rand::{Rng, RngCore};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    errors::AppError,
+    utils::{refresh_token::hash_token, totp},
+};
+
+/// Number of recovery codes issued when 2FA is enabled
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Characters of a recovery code, without look-alikes such as 0/o and 1/l
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Generate a recovery code formatted as "xxxxx-xxxxx"
+fn generate_recovery_code() -> String {
+    let mut rng = rand::rng();
+    let chars: String = (0..10)
+        .map(|_| RECOVERY_CODE_ALPHABET[rng.random_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+        .collect();
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+/// Recovery codes are compared without separators, whitespace or case
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Replace a user's recovery codes with a fresh set, returned in plain text once
+pub async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<String>, AppError> {
+    sqlx::query!(
+        "DELETE FROM totp_recovery_codes WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| hash_token(&normalize_recovery_code(code)))
+        .collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO totp_recovery_codes (user_id, code_hash)
+        SELECT $1, UNNEST($2::text[])
+        "#,
+        user_id,
+        &hashes
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(codes)
+}
+
+/// Check a TOTP code or an unused recovery code for a user with 2FA enabled.
+///
+/// A matching TOTP step is recorded and a matching recovery code is spent,
+/// so neither can be used twice. Run inside a transaction.
+pub async fn check_second_factor(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, AppError> {
+    // Lock the user row so two requests cannot accept the same TOTP step
+    let user = sqlx::query!(
+        r#"
+        SELECT totp_secret, totp_last_step FROM users
+        WHERE id = $1 AND totp_enabled_at IS NOT NULL
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(user) = user else {
+        return Ok(false);
+    };
+    let Some(secret) = user.totp_secret else {
+        return Ok(false);
+    };
+
+    let code = code.trim();
+    if totp::is_totp_code(code) {
+        let Some(step) = totp::verify_code(&secret, code, user.totp_last_step) else {
+            return Ok(false);
+        };
+
+        sqlx::query!(
+            "UPDATE users SET totp_last_step = $1 WHERE id = $2",
+            step,
+            user_id
+        )
+        .execute(&mut *conn)
+        .await?;
+        return Ok(true);
+    }
+
+    let used = sqlx::query!(
+        r#"
+        UPDATE totp_recovery_codes SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        hash_token(&normalize_recovery_code(code))
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    if used.rows_affected() > 0 {
+        tracing::info!("User {} signed in with a recovery code", user_id);
+    }
+    Ok(used.rows_affected() > 0)
+}
+
+/// Issue a challenge token for the second login step
+pub async fn create_login_challenge(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    ttl_secs: i64,
+) -> Result<String, AppError> {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO login_challenges (user_id, token_hash, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+        user_id,
+        hash_token(&token),
+        Utc::now() + Duration::seconds(ttl_secs)
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(token)
+}
+
+/// User a pending login challenge belongs to
+pub async fn login_challenge_user(db: &PgPool, token: &str) -> Result<Uuid, AppError> {
+    let challenge = sqlx::query!(
+        r#"
+        SELECT user_id, expires_at FROM login_challenges
+        WHERE token_hash = $1 AND consumed_at IS NULL
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(AppError::InvalidToken)?;
+
+    if challenge.expires_at < Utc::now() {
+        return Err(AppError::TokenExpired);
+    }
+
+    Ok(challenge.user_id)
+}
+
+/// Complete a login challenge with a TOTP or recovery code.
+///
+/// Every wrong code counts against the challenge; after `max_attempts` the
+/// user has to start over with their password. Returns whether the code was
+/// accepted.
+pub async fn complete_login_challenge(
+    db: &PgPool,
+    token: &str,
+    code: &str,
+    max_attempts: i32,
+) -> Result<bool, AppError> {
+    let mut tx = db.begin().await?;
+
+    let challenge = sqlx::query!(
+        r#"
+        SELECT id, user_id, attempts, expires_at FROM login_challenges
+        WHERE token_hash = $1 AND consumed_at IS NULL
+        FOR UPDATE
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(AppError::InvalidToken)?;
+
+    if challenge.expires_at < Utc::now() {
+        return Err(AppError::TokenExpired);
+    }
+
+    if challenge.attempts >= max_attempts {
+        return Err(AppError::OtpLocked);
+    }
+
+    if !check_second_factor(&mut tx, challenge.user_id, code).await? {
+        sqlx::query!(
+            "UPDATE login_challenges SET attempts = attempts + 1 WHERE id = $1",
+            challenge.id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "UPDATE login_challenges SET consumed_at = NOW() WHERE id = $1",
+        challenge.id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Remove login challenges that were used or have expired
+pub async fn delete_stale_login_challenges(db: &PgPool) -> Result<u64, AppError> {
+    let deleted = sqlx::query!(
+        "DELETE FROM login_challenges WHERE consumed_at IS NOT NULL OR expires_at < NOW()"
+    )
+    .execute(db)
+    .await?
+    .rows_affected();
+
+    Ok(deleted)
+}
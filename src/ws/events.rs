@@ -0,0 +1,28 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::joint::JointRole;
+
+/// Notifications pushed to the clients of a joint besides chat messages.
+///
+/// Serialized with a `type` field; chat messages have none.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JointEvent {
+    /// A member's role changed
+    RoleChanged {
+        joint_id: Uuid,
+        user_id: Uuid,
+        role: JointRole,
+        changed_by: Uuid,
+    },
+    /// A member was kicked, banned or left (`removed_by` is themselves); their
+    /// connection is closed right after
+    MemberRemoved {
+        joint_id: Uuid,
+        user_id: Uuid,
+        /// Whether they may join again
+        banned: bool,
+        removed_by: Uuid,
+    },
+}
@@ -12,7 +12,10 @@ use sqlx::PgPool;
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
-use crate::ws::manager::ConnectionManager;
+use crate::{
+    errors::AppError, middleware::ws_auth::WsSession, utils::revocation::RevocationCache,
+    ws::manager::ConnectionManager,
+};
 
 /// Incoming message from client
 #[derive(Debug, Deserialize)]
@@ -35,13 +38,31 @@ struct OutgoingMessage {
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<crate::models::app_state::AppState>,
-    Extension(user_id): Extension<Uuid>,
-    Extension(joint_id): Extension<Uuid>,
-    Extension(username): Extension<String>,
-) -> Response {
+    Extension(session): Extension<WsSession>,
+) -> Result<Response, AppError> {
     let manager = state.ws_manager.clone();
+    let joint_id = session.joint_id;
+
+    // Refuse new connections once the joint is at capacity
+    if manager.get_joint_connection_count(joint_id).await >= state.config.max_joint_connections {
+        return Err(AppError::ValidationError(
+            "This joint has reached its connection limit".to_string(),
+        ));
+    }
+
     let db = state.db.clone();
-    ws.on_upgrade(move |socket| handle_socket(socket, manager, db, user_id, joint_id, username))
+    let revocations = state.revocations.clone();
+    let max_message_length = state.config.max_message_length;
+    Ok(ws.on_upgrade(move |socket| {
+        handle_socket(
+            socket,
+            manager,
+            db,
+            revocations,
+            session,
+            max_message_length,
+        )
+    }))
 }
 
 /// Handle individual WebSocket connection
@@ -49,27 +70,40 @@ async fn handle_socket(
     socket: WebSocket,
     manager: ConnectionManager,
     db: PgPool,
-    user_id: Uuid,
-    joint_id: Uuid,
-    username: String,
+    revocations: RevocationCache,
+    session: WsSession,
+    max_message_length: usize,
 ) {
+    let WsSession {
+        user_id,
+        joint_id,
+        username,
+        claims,
+    } = session;
+
     let (mut sender, mut receiver) = socket.split();
 
+    // Counted until this function returns, so shutdown can wait for it
+    let _socket = manager.track_socket();
+
     // Create channel for this client
     let (tx, mut rx) = mpsc::unbounded_channel::<String>();
 
     // Register client in manager
-    manager
-        .add_client(user_id, username.clone(), joint_id, tx)
+    let connection_id = manager
+        .add_client(user_id, username.clone(), joint_id, claims.jti, tx)
         .await;
 
     // Task to send messages TO client
     let mut send_task = tokio::spawn(async move {
         while let Some(message) = rx.recv().await {
             if sender.send(Message::Text(message.into())).await.is_err() {
-                break;
+                return;
             }
         }
+
+        // Channel closed by the manager (session revoked): close the socket
+        let _ = sender.send(Message::Close(None)).await;
     });
 
     // Task to receive messages FROM client
@@ -80,6 +114,34 @@ async fn handle_socket(
             if let Message::Text(text) = message {
                 // Parse incoming message
                 if let Ok(incoming) = serde_json::from_str::<IncomingMessage>(&text) {
+                    // Drop messages over the configured length limit
+                    if incoming.content.chars().count() > max_message_length {
+                        tracing::warn!("Dropping oversized message from user {}", user_id);
+                        continue;
+                    }
+
+                    // Logout closes the socket too, but may have happened on
+                    // another instance
+                    match revocations.is_revoked(&db, user_id, &claims).await {
+                        Ok(false) => {}
+                        Ok(true) => break,
+                        Err(e) => {
+                            tracing::error!("Failed to check token revocation: {:?}", e);
+                            continue;
+                        }
+                    }
+
+                    // Kicks and bans also close the socket; check again in case
+                    // the message raced them
+                    match may_post(&db, joint_id, user_id).await {
+                        Ok(true) => {}
+                        Ok(false) => break,
+                        Err(e) => {
+                            tracing::error!("Failed to check membership: {:?}", e);
+                            continue;
+                        }
+                    }
+
                     // Save message to database
                     let message_id = Uuid::new_v4();
                     let created_at = chrono::Utc::now();
@@ -132,6 +194,27 @@ async fn handle_socket(
     }
 
     // Cleanup: remove client from manager
-    manager.remove_client(&user_id).await;
+    manager.remove_client(&connection_id).await;
     tracing::info!("User {} disconnected from joint {}", username, joint_id);
 }
+
+/// Whether the user is still a member of the joint and not banned from it
+async fn may_post(db: &PgPool, joint_id: Uuid, user_id: Uuid) -> Result<bool, AppError> {
+    let allowed = sqlx::query_scalar!(
+        r#"
+        SELECT
+            EXISTS(SELECT 1 FROM joint_members WHERE joint_id = $1 AND user_id = $2)
+            AND NOT EXISTS(
+                SELECT 1 FROM joint_bans
+                WHERE joint_id = $1 AND user_id = $2
+                AND (expires_at IS NULL OR expires_at > NOW())
+            ) as "allowed!"
+        "#,
+        joint_id,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(allowed)
+}
@@ -1,52 +1,89 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Notify, RwLock, mpsc};
 use uuid::Uuid;
 
+use crate::ws::events::JointEvent;
+
 /// Connected client info
 pub struct Client {
     pub user_id: Uuid,
     pub username: String,
     pub joint_id: Uuid,
+    /// ID of the access token the connection was opened with
+    pub token_id: Uuid,
     pub sender: mpsc::UnboundedSender<String>,
 }
 
 /// Manages all WebSocket connections
 #[derive(Clone)]
 pub struct ConnectionManager {
-    // Map: user_id -> Client
+    // Map: connection_id -> Client; a user may hold several connections
     clients: Arc<RwLock<HashMap<Uuid, Client>>>,
+    // Sockets still running, including ones already removed from `clients`
+    open_sockets: Arc<AtomicUsize>,
+    socket_closed: Arc<Notify>,
+}
+
+/// Keeps a socket counted as open until dropped
+pub struct SocketGuard {
+    open_sockets: Arc<AtomicUsize>,
+    socket_closed: Arc<Notify>,
+}
+
+impl Drop for SocketGuard {
+    fn drop(&mut self) {
+        self.open_sockets.fetch_sub(1, Ordering::SeqCst);
+        self.socket_closed.notify_waiters();
+    }
 }
 
 impl ConnectionManager {
     pub fn new() -> Self {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
+            open_sockets: Arc::new(AtomicUsize::new(0)),
+            socket_closed: Arc::new(Notify::new()),
         }
     }
 
-    /// Add a new client connection
+    /// Count a socket as open for `wait_for_drain` until the guard is dropped
+    pub fn track_socket(&self) -> SocketGuard {
+        self.open_sockets.fetch_add(1, Ordering::SeqCst);
+        SocketGuard {
+            open_sockets: self.open_sockets.clone(),
+            socket_closed: self.socket_closed.clone(),
+        }
+    }
+
+    /// Add a new client connection; returns its id for `remove_client`
     pub async fn add_client(
         &self,
         user_id: Uuid,
         username: String,
         joint_id: Uuid,
+        token_id: Uuid,
         sender: mpsc::UnboundedSender<String>,
-    ) {
+    ) -> Uuid {
+        let connection_id = Uuid::new_v4();
         let client = Client {
             user_id,
             username: username.clone(),
             joint_id,
+            token_id,
             sender,
         };
 
-        self.clients.write().await.insert(user_id, client);
+        self.clients.write().await.insert(connection_id, client);
         tracing::info!("User {} connected to joint {}", username, joint_id);
+        connection_id
     }
 
     /// Remove a client connection
-    pub async fn remove_client(&self, user_id: &Uuid) {
-        if let Some(client) = self.clients.write().await.remove(user_id) {
+    pub async fn remove_client(&self, connection_id: &Uuid) {
+        if let Some(client) = self.clients.write().await.remove(connection_id) {
             tracing::info!(
                 "User {} disconnected from joint {}",
                 client.username,
@@ -55,20 +92,111 @@ impl ConnectionManager {
         }
     }
 
+    /// Close connections opened with a revoked access token
+    pub async fn disconnect_token(&self, token_id: Uuid) {
+        self.disconnect_where(|client| client.token_id == token_id)
+            .await;
+    }
+
+    /// Close every connection of a user
+    pub async fn disconnect_user(&self, user_id: Uuid) {
+        self.disconnect_where(|client| client.user_id == user_id)
+            .await;
+    }
+
+    /// Close a user's connection to one joint, e.g. when they are kicked
+    pub async fn disconnect_from_joint(&self, user_id: Uuid, joint_id: Uuid) {
+        self.disconnect_where(|client| client.user_id == user_id && client.joint_id == joint_id)
+            .await;
+    }
+
+    /// Close every connection to a joint, e.g. when it is closed
+    pub async fn disconnect_joint(&self, joint_id: Uuid) {
+        self.disconnect_where(|client| client.joint_id == joint_id)
+            .await;
+    }
+
+    /// Close every connection, e.g. on shutdown
+    pub async fn close_all(&self) {
+        self.disconnect_where(|_| true).await;
+    }
+
+    /// Wait until every socket has finished, giving up after `timeout`.
+    ///
+    /// Returns the number of sockets still open.
+    pub async fn wait_for_drain(&self, timeout: Duration) -> usize {
+        let drained = async {
+            loop {
+                let closed = self.socket_closed.notified();
+                if self.open_sockets.load(Ordering::SeqCst) == 0 {
+                    return;
+                }
+                closed.await;
+            }
+        };
+
+        let _ = tokio::time::timeout(timeout, drained).await;
+        self.open_sockets.load(Ordering::SeqCst)
+    }
+
+    /// Drop matching clients; dropping the sender makes the socket task close the connection
+    async fn disconnect_where(&self, predicate: impl Fn(&Client) -> bool) {
+        self.clients.write().await.retain(|_, client| {
+            let disconnect = predicate(client);
+            if disconnect {
+                tracing::info!(
+                    "Closing connection of user {} to joint {}",
+                    client.username,
+                    client.joint_id
+                );
+            }
+            !disconnect
+        });
+    }
+
     /// Broadcast message to all users in a joint
     pub async fn broadcast_to_joint(&self, joint_id: Uuid, message: String, sender_id: Uuid) {
         let clients = self.clients.read().await;
 
-        for (user_id, client) in clients.iter() {
+        for client in clients.values() {
             // Send to all users in the joint EXCEPT the sender
-            if client.joint_id == joint_id && *user_id != sender_id {
-                if let Err(e) = client.sender.send(message.clone()) {
-                    tracing::error!("Failed to send message to user {}: {}", user_id, e);
-                }
+            if client.joint_id == joint_id
+                && client.user_id != sender_id
+                && let Err(e) = client.sender.send(message.clone())
+            {
+                tracing::error!("Failed to send message to user {}: {}", client.user_id, e);
             }
         }
     }
 
+    /// Send an event to every user in a joint
+    pub async fn send_event(&self, joint_id: Uuid, event: &JointEvent) {
+        let message = match serde_json::to_string(event) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::error!("Failed to serialize joint event: {}", e);
+                return;
+            }
+        };
+
+        let clients = self.clients.read().await;
+        for client in clients.values().filter(|c| c.joint_id == joint_id) {
+            if let Err(e) = client.sender.send(message.clone()) {
+                tracing::error!("Failed to send event to user {}: {}", client.user_id, e);
+            }
+        }
+    }
+
+    /// Get count of open connections to a joint
+    pub async fn get_joint_connection_count(&self, joint_id: Uuid) -> usize {
+        self.clients
+            .read()
+            .await
+            .values()
+            .filter(|c| c.joint_id == joint_id)
+            .count()
+    }
+
     /// Get count of online users in a joint
     pub async fn get_joint_user_count(&self, joint_id: Uuid) -> usize {
         self.clients
@@ -76,7 +204,9 @@ impl ConnectionManager {
             .await
             .values()
             .filter(|c| c.joint_id == joint_id)
-            .count()
+            .map(|c| c.user_id)
+            .collect::<HashSet<_>>()
+            .len()
     }
 }
 
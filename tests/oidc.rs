@@ -0,0 +1,452 @@
+//! Sign-in with an external identity provider, against a mock provider that
+//! serves a discovery document, a JWKS and a token endpoint on localhost.
+//!
+//! The end-to-end tests also need a database and are skipped unless
+//! `DATABASE_URL` is set.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use aws_lc_rs::rand::SystemRandom;
+use aws_lc_rs::signature::{Ed25519KeyPair, KeyPair};
+use axum::{
+    Form, Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode};
+use serde_json::{Value, json};
+use url::Url;
+use uuid::Uuid;
+
+use spot_feed::app::{build_app, init_state};
+use spot_feed::config::{AppConfig, OidcProviderConfig};
+use spot_feed::errors::AppError;
+use spot_feed::oidc::OidcClient;
+use spot_feed::oidc::id_token::IdTokenClaims;
+use spot_feed::oidc::pkce::code_challenge;
+
+const PROVIDER: &str = "mock";
+const CLIENT_ID: &str = "spot-feed-test";
+const KEY_ID: &str = "mock-key";
+
+/// Ed25519 key the mock provider signs ID tokens with
+struct SigningKey {
+    encoding_key: EncodingKey,
+    public_key: Vec<u8>,
+}
+
+impl SigningKey {
+    fn generate() -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+        Self {
+            encoding_key: EncodingKey::from_ed_der(pkcs8.as_ref()),
+            public_key: key_pair.public_key().as_ref().to_vec(),
+        }
+    }
+
+    fn sign(&self, claims: &Value) -> String {
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(KEY_ID.to_string());
+        encode(&header, claims, &self.encoding_key).unwrap()
+    }
+}
+
+/// What the provider remembers about an authorization code it handed out
+struct Grant {
+    code_challenge: String,
+    claims: Value,
+}
+
+struct MockProvider {
+    issuer: String,
+    /// Key published in the JWKS
+    published_key: SigningKey,
+    /// Key ID tokens are signed with; differs from the published one when
+    /// a test wants a forged token
+    signing_key: SigningKey,
+    grants: Mutex<HashMap<String, Grant>>,
+}
+
+impl MockProvider {
+    /// Start the provider on a free port
+    async fn start() -> Arc<Self> {
+        Self::start_with_forged_signatures(false).await
+    }
+
+    async fn start_with_forged_signatures(forged: bool) -> Arc<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let published_key = SigningKey::generate();
+        let signing_key = if forged {
+            SigningKey::generate()
+        } else {
+            SigningKey {
+                encoding_key: published_key.encoding_key.clone(),
+                public_key: published_key.public_key.clone(),
+            }
+        };
+
+        let provider = Arc::new(Self {
+            issuer: format!("http://{}", listener.local_addr().unwrap()),
+            published_key,
+            signing_key,
+            grants: Mutex::new(HashMap::new()),
+        });
+
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/token", post(token))
+            .with_state(provider.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        provider
+    }
+
+    fn config(&self) -> OidcProviderConfig {
+        serde_json::from_value(self.config_json()).unwrap()
+    }
+
+    fn config_json(&self) -> Value {
+        json!({
+            "name": PROVIDER,
+            "issuer": self.issuer,
+            "client_id": CLIENT_ID,
+            "redirect_uri": "http://localhost:3000/callback",
+        })
+    }
+
+    /// Play the user signing in at the authorization URL: returns the code
+    /// the provider redirects back with, redeemable for an ID token that
+    /// carries `claims` plus the usual registered claims
+    fn authorize(&self, authorization_url: &str, claims: Value) -> String {
+        let url = Url::parse(authorization_url).unwrap();
+        assert!(
+            authorization_url.starts_with(&format!("{}/authorize?", self.issuer)),
+            "unexpected authorization URL {}",
+            authorization_url
+        );
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["response_type"], "code");
+        assert_eq!(query["client_id"], CLIENT_ID);
+        assert_eq!(query["code_challenge_method"], "S256");
+
+        let now = chrono::Utc::now().timestamp();
+        let mut id_token_claims = json!({
+            "iss": self.issuer,
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": query["nonce"],
+        });
+        for (name, value) in claims.as_object().unwrap() {
+            id_token_claims[name] = value.clone();
+        }
+
+        let code = Uuid::new_v4().to_string();
+        self.grants.lock().unwrap().insert(
+            code.clone(),
+            Grant {
+                code_challenge: query["code_challenge"].clone(),
+                claims: id_token_claims,
+            },
+        );
+        code
+    }
+}
+
+async fn discovery(State(provider): State<Arc<MockProvider>>) -> Json<Value> {
+    Json(json!({
+        "issuer": provider.issuer,
+        "authorization_endpoint": format!("{}/authorize", provider.issuer),
+        "token_endpoint": format!("{}/token", provider.issuer),
+        "jwks_uri": format!("{}/jwks", provider.issuer),
+    }))
+}
+
+async fn jwks(State(provider): State<Arc<MockProvider>>) -> Json<Value> {
+    Json(json!({
+        "keys": [{
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "use": "sig",
+            "kid": KEY_ID,
+            "x": URL_SAFE_NO_PAD.encode(&provider.published_key.public_key),
+        }]
+    }))
+}
+
+async fn token(
+    State(provider): State<Arc<MockProvider>>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    let grant = form
+        .get("code")
+        .and_then(|code| provider.grants.lock().unwrap().remove(code));
+    let verified = grant.filter(|grant| {
+        form.get("grant_type").map(String::as_str) == Some("authorization_code")
+            && form.get("client_id").map(String::as_str) == Some(CLIENT_ID)
+            && form
+                .get("code_verifier")
+                .is_some_and(|verifier| code_challenge(verifier) == grant.code_challenge)
+    });
+
+    match verified {
+        Some(grant) => Json(json!({
+            "access_token": "unused",
+            "token_type": "Bearer",
+            "id_token": provider.signing_key.sign(&grant.claims),
+        }))
+        .into_response(),
+        None => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "invalid_grant" })),
+        )
+            .into_response(),
+    }
+}
+
+/// Run the client side of a sign-in, returning the exchange result
+async fn sign_in(
+    provider: &MockProvider,
+    claims: Value,
+    nonce: &str,
+    code_verifier: &str,
+) -> Result<IdTokenClaims, AppError> {
+    let client = OidcClient::new(&[provider.config()]);
+    let authorization_url = client
+        .authorization_url(PROVIDER, "state", nonce, &code_challenge(code_verifier))
+        .await?;
+    let code = provider.authorize(&authorization_url, claims);
+
+    client
+        .exchange_code(PROVIDER, &code, code_verifier, nonce)
+        .await
+}
+
+#[tokio::test]
+async fn exchanges_code_for_validated_claims() {
+    let provider = MockProvider::start().await;
+
+    let claims = sign_in(
+        &provider,
+        json!({ "sub": "user-1", "email": "Someone@Example.com", "email_verified": "true" }),
+        "nonce-1",
+        "verifier-1",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(claims.sub, "user-1");
+    assert_eq!(claims.verified_email(), Some("Someone@Example.com"));
+}
+
+#[tokio::test]
+async fn rejects_code_redeemed_with_wrong_verifier() {
+    let provider = MockProvider::start().await;
+    let client = OidcClient::new(&[provider.config()]);
+
+    let authorization_url = client
+        .authorization_url(PROVIDER, "state", "nonce", &code_challenge("verifier"))
+        .await
+        .unwrap();
+    let code = provider.authorize(&authorization_url, json!({ "sub": "user-1" }));
+
+    let result = client
+        .exchange_code(PROVIDER, &code, "another-verifier", "nonce")
+        .await;
+    assert!(matches!(result, Err(AppError::InvalidToken)));
+}
+
+#[tokio::test]
+async fn rejects_id_token_for_another_sign_in() {
+    let provider = MockProvider::start().await;
+    let client = OidcClient::new(&[provider.config()]);
+
+    let authorization_url = client
+        .authorization_url(PROVIDER, "state", "nonce", &code_challenge("verifier"))
+        .await
+        .unwrap();
+    let code = provider.authorize(&authorization_url, json!({ "sub": "user-1" }));
+
+    let result = client
+        .exchange_code(PROVIDER, &code, "verifier", "other-nonce")
+        .await;
+    assert!(matches!(result, Err(AppError::InvalidToken)));
+}
+
+#[tokio::test]
+async fn rejects_id_token_with_wrong_audience() {
+    let provider = MockProvider::start().await;
+
+    let result = sign_in(
+        &provider,
+        json!({ "sub": "user-1", "aud": "another-client" }),
+        "nonce",
+        "verifier",
+    )
+    .await;
+    assert!(matches!(result, Err(AppError::InvalidToken)));
+}
+
+#[tokio::test]
+async fn rejects_id_token_signed_with_unpublished_key() {
+    let provider = MockProvider::start_with_forged_signatures(true).await;
+
+    let result = sign_in(&provider, json!({ "sub": "user-1" }), "nonce", "verifier").await;
+    assert!(matches!(result, Err(AppError::InvalidToken)));
+}
+
+/// Server with the mock provider configured, or None without a database
+async fn start_server(provider: &MockProvider) -> Option<(String, sqlx::PgPool)> {
+    let Ok(database_url) = std::env::var("DATABASE_URL") else {
+        eprintln!("DATABASE_URL is not set, skipping");
+        return None;
+    };
+
+    let settings = HashMap::from([
+        (
+            "JWT_SECRET",
+            "oidc-test-secret-that-is-long-enough".to_string(),
+        ),
+        ("EMAIL_TRANSPORT", "memory".to_string()),
+        (
+            "OIDC_PROVIDERS",
+            json!([provider.config_json()]).to_string(),
+        ),
+        // Sign-ups hash a random password; keep that cheap
+        ("ARGON2_MEMORY_KIB", "8".to_string()),
+        ("ARGON2_ITERATIONS", "1".to_string()),
+    ]);
+    let config = AppConfig::load(|key| settings.get(key).cloned()).unwrap();
+
+    let state = init_state(&database_url, config).await;
+    let db = state.db.clone();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let app = build_app(state).into_make_service_with_connect_info::<SocketAddr>();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    Some((address, db))
+}
+
+/// Sign in through the API, returning the callback's status and body
+async fn api_sign_in(server: &str, provider: &MockProvider, claims: Value) -> (StatusCode, Value) {
+    let http = reqwest::Client::new();
+
+    let started: Value = http
+        .post(format!(
+            "{}/api/v1/auth/oidc/{}/authorize",
+            server, PROVIDER
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let code = provider.authorize(started["authorization_url"].as_str().unwrap(), claims);
+
+    let response = http
+        .post(format!("{}/api/v1/auth/oidc/{}/callback", server, PROVIDER))
+        .json(&json!({
+            "code": code,
+            "state": started["state"],
+            "is_18_plus": true,
+        }))
+        .send()
+        .await
+        .unwrap();
+    let status = StatusCode::from_u16(response.status().as_u16()).unwrap();
+    (status, response.json().await.unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn links_identity_to_account_with_verified_email() {
+    let provider = MockProvider::start().await;
+    let Some((server, db)) = start_server(&provider).await else {
+        return;
+    };
+
+    let suffix = Uuid::new_v4().simple().to_string();
+    let email = format!("oidc-{}@example.com", &suffix[..12]);
+    let user_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO users (username, email, password_hash, is_18_plus, is_verified)
+        VALUES ($1, $2, 'unused', true, true)
+        RETURNING id
+        "#,
+    )
+    .bind(format!("oidc_{}", &suffix[..12]))
+    .bind(&email)
+    .fetch_one(&db)
+    .await
+    .unwrap();
+
+    let subject = format!("sub-{}", suffix);
+    let claims = json!({ "sub": subject, "email": email.to_uppercase(), "email_verified": true });
+    let (status, body) = api_sign_in(&server, &provider, claims.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body["access_token"].is_string());
+
+    let linked: Uuid = sqlx::query_scalar(
+        "SELECT user_id FROM oidc_identities WHERE provider = $1 AND subject = $2",
+    )
+    .bind(PROVIDER)
+    .bind(&subject)
+    .fetch_one(&db)
+    .await
+    .unwrap();
+    assert_eq!(linked, user_id);
+
+    // Later sign-ins find the linked identity
+    let (status, body) = api_sign_in(&server, &provider, claims).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(&db)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn creates_account_with_normalized_username() {
+    let provider = MockProvider::start().await;
+    let Some((server, db)) = start_server(&provider).await else {
+        return;
+    };
+
+    let suffix = &Uuid::new_v4().simple().to_string()[..12];
+    let email = format!("oidc-new-{}@example.com", suffix);
+    let subject = format!("sub-{}", suffix);
+    let claims = json!({
+        "sub": subject,
+        "email": email,
+        "email_verified": true,
+        "preferred_username": format!("  new {}! ", suffix),
+    });
+
+    let (status, body) = api_sign_in(&server, &provider, claims).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (user_id, username): (Uuid, String) =
+        sqlx::query_as("SELECT id, username FROM users WHERE email = $1")
+            .bind(&email)
+            .fetch_one(&db)
+            .await
+            .unwrap();
+    assert_eq!(username, format!("new{}", suffix));
+
+    sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(&db)
+        .await
+        .unwrap();
+}